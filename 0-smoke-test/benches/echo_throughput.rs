@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use protohackers_0_smoke_test::echo::{copy_echo, framed_echo, EchoMode};
+use protohackers_utils::{set_inspect_enabled, ConnectionContext};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime::Runtime,
+};
+
+const PAYLOAD_SIZES: [usize; 2] = [64 * 1024, 8 * 1024 * 1024];
+
+const WRITE_CHUNK_SIZE: usize = 16 * 1024;
+
+async fn serve(listener: TcpListener, mode: EchoMode) {
+    loop {
+        let (mut stream, peer_addr) = listener.accept().await.unwrap();
+        let ctx = ConnectionContext::new(peer_addr, stream.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            match mode {
+                EchoMode::Framed => framed_echo(stream, ctx).await.unwrap(),
+                EchoMode::Copy => {
+                    let (read, mut write) = stream.split();
+                    copy_echo(read, &mut write).await.unwrap();
+                }
+            }
+        });
+    }
+}
+
+/// Sends `payload` in chunks, half-closes, and reads the echo back to EOF.
+async fn round_trip(addr: SocketAddr, payload: &[u8]) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.set_nodelay(true).unwrap();
+    let (mut read, mut write) = stream.split();
+
+    let send = async {
+        for chunk in payload.chunks(WRITE_CHUNK_SIZE) {
+            write.write_all(chunk).await.unwrap();
+        }
+        write.shutdown().await.unwrap();
+    };
+    let mut echoed = Vec::with_capacity(payload.len());
+    let receive = read.read_to_end(&mut echoed);
+
+    let ((), received) = tokio::join!(send, receive);
+    assert_eq!(received.unwrap(), payload.len());
+}
+
+fn echo_throughput(c: &mut Criterion) {
+    set_inspect_enabled(false);
+
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("echo_throughput");
+
+    for mode in [EchoMode::Framed, EchoMode::Copy] {
+        let listener = runtime
+            .block_on(TcpListener::bind((Ipv4Addr::LOCALHOST, 0)))
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(serve(listener, mode));
+
+        for size in PAYLOAD_SIZES {
+            let payload = (0..=255).cycle().take(size).collect::<Vec<u8>>();
+
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{mode:?}"), size),
+                &payload,
+                |b, payload| b.iter(|| runtime.block_on(round_trip(addr, payload))),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, echo_throughput);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use futures::StreamExt;
+use protohackers_utils::{ConnectionContext, InspectCodec};
+use std::str::FromStr;
+use thiserror::Error;
+use tokio::io::{self, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_util::codec::{BytesCodec, Framed};
+
+/// Size of the single buffer reused by [`copy_echo`] for the whole connection.
+pub const COPY_ECHO_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How TCP echo moves bytes around.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EchoMode {
+    /// Frame by frame, so that traffic can be inspected.
+    #[default]
+    Framed,
+    /// Straight from the socket's read half to its write half, see [`copy_echo`].
+    Copy,
+}
+
+#[derive(Debug, Error)]
+#[error("unknown echo mode {0:?}, expected framed or copy")]
+pub struct UnknownEchoMode(String);
+
+impl FromStr for EchoMode {
+    type Err = UnknownEchoMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "framed" => Ok(Self::Framed),
+            "copy" => Ok(Self::Copy),
+            _ => Err(UnknownEchoMode(s.to_owned())),
+        }
+    }
+}
+
+/// Echoes through a [`BytesCodec`], allocating a frame per read.
+pub async fn framed_echo<S>(stream: S, ctx: ConnectionContext) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (write, read) = Framed::new(stream, InspectCodec::new(BytesCodec::new(), ctx)).split();
+    read.forward(write).await
+}
+
+/// Echoes everything read from `read` into `write` through one reused buffer, then shuts `write`
+/// down once `read` reaches EOF. Returns the number of bytes echoed.
+pub async fn copy_echo<R, W>(read: R, write: &mut W) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut read = BufReader::with_capacity(COPY_ECHO_BUFFER_SIZE, read);
+    let echoed = io::copy_buf(&mut read, write).await?;
+
+    // The client's EOF only closes its half, so it still gets everything echoed before ours
+    write.shutdown().await?;
+
+    Ok(echoed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_copy_echo_half_close() {
+        let (mut client, server) = io::duplex(1024);
+        let (server_read, mut server_write) = io::split(server);
+        let server = tokio::spawn(async move { copy_echo(server_read, &mut server_write).await });
+
+        // More than fits in the pipe, so the echo has to keep going while the client writes
+        let sent = (0..=255).cycle().take(100_000).collect::<Vec<u8>>();
+        let (mut client_read, mut client_write) = io::split(&mut client);
+        let write = async {
+            client_write.write_all(&sent).await?;
+            client_write.shutdown().await
+        };
+        let mut received = Vec::new();
+        let (written, read) = tokio::join!(write, client_read.read_to_end(&mut received));
+        written.unwrap();
+        read.unwrap();
+
+        assert_eq!(received, sent);
+        assert_eq!(server.await.unwrap().unwrap(), sent.len() as u64);
+    }
+}
@@ -1,15 +1,152 @@
-use futures::StreamExt;
-use protohackers_utils::default_tcp_listen;
-use std::net::SocketAddr;
-use tokio::{io, net::TcpStream};
-use tokio_util::codec::{BytesCodec, Framed};
-
-async fn handle_client(stream: TcpStream, _: SocketAddr) -> io::Result<()> {
-    let (write, read) = Framed::new(stream, BytesCodec::new()).split();
-    read.forward(write).await
+mod service;
+
+use futures::future::try_join_all;
+use protohackers_0_smoke_test::echo::{copy_echo, framed_echo, EchoMode};
+use protohackers_utils::{
+    inspect_bytes, tcp_listen_on_port, ConnectionContext, ConnectionStream, Direction,
+    PeerRateLimiter, RateLimit, DEFAULT_PORT,
+};
+use service::{daytime, parse_services, quote, time, Chargen, Service};
+use std::{
+    env,
+    net::{IpAddr, Ipv4Addr},
+    time::SystemTime,
+};
+use tokio::{
+    io::{self, AsyncWriteExt, BufWriter},
+    net::UdpSocket,
+};
+
+/// Comma-separated `service=port` pairs to serve, e.g. `echo=7,discard=9,chargen=19`. Defaults to
+/// echo on the usual port.
+const SERVICES_ENV_VAR: &str = "SMOKE_TEST_SERVICES";
+
+/// How TCP echo is served, `framed` (the default, inspectable) or `copy` (faster).
+const ECHO_MODE_ENV_VAR: &str = "SMOKE_TEST_ECHO_MODE";
+
+/// Replying to spoofed datagrams makes for an easy amplifier, chargen's and qotd's especially.
+/// Limited per IP, since a spoofer can pick any source port.
+const UDP_RATE_LIMIT_BURST: u32 = 16;
+const UDP_RATE_LIMIT_PER_SECOND: u32 = 8;
+
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// RFC 864 datagrams hold up to 512 characters.
+const CHARGEN_DATAGRAM_LINES: usize = 6;
+
+async fn handle_client(
+    service: Service,
+    echo_mode: EchoMode,
+    mut stream: ConnectionStream,
+    ctx: ConnectionContext,
+) -> io::Result<()> {
+    match (service, echo_mode) {
+        (Service::Echo, EchoMode::Framed) => return framed_echo(stream, ctx).await,
+        (Service::Echo, EchoMode::Copy) => {
+            let (read, mut write) = stream.split();
+            copy_echo(read, &mut write).await?;
+            return Ok(());
+        }
+        (Service::Discard, _) => {
+            io::copy(&mut stream, &mut io::sink()).await?;
+            return Ok(());
+        }
+        (Service::Chargen, _) => {
+            // Until the client goes away, ignoring whatever it sends
+            let mut stream = BufWriter::new(stream);
+            let mut chargen = Chargen::default();
+
+            loop {
+                stream.write_all(&chargen.next_line()).await?;
+            }
+        }
+        (Service::Daytime, _) => {
+            stream
+                .write_all(daytime(SystemTime::now()).as_bytes())
+                .await?
+        }
+        (Service::Time, _) => stream.write_all(&time(SystemTime::now())).await?,
+        (Service::Qotd, _) => {
+            stream
+                .write_all(quote(SystemTime::now()).as_bytes())
+                .await?
+        }
+    }
+
+    stream.shutdown().await
+}
+
+async fn serve_udp(service: Service, port: u16) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).await?;
+    println!("Listening on {} (UDP)", socket.local_addr()?);
+
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    let mut chargen = Chargen::default();
+    let mut rate_limiter = PeerRateLimiter::<IpAddr>::new(RateLimit::per_second(
+        UDP_RATE_LIMIT_BURST,
+        UDP_RATE_LIMIT_PER_SECOND,
+    ));
+
+    loop {
+        let (read, peer) = socket.recv_from(&mut buf).await?;
+        let request = &buf[..read];
+
+        inspect_bytes(peer, Direction::Received, request);
+
+        if service == Service::Discard {
+            continue;
+        }
+
+        if rate_limiter.try_acquire(peer.ip()).is_err() {
+            println!("Dropping datagram from {peer}: rate limit exceeded");
+            continue;
+        }
+
+        let response = match service {
+            Service::Echo => request.to_owned(),
+            Service::Discard => unreachable!(),
+            Service::Chargen => (0..CHARGEN_DATAGRAM_LINES)
+                .flat_map(|_| chargen.next_line())
+                .collect(),
+            Service::Daytime => daytime(SystemTime::now()).into_bytes(),
+            Service::Time => time(SystemTime::now()).to_vec(),
+            Service::Qotd => quote(SystemTime::now()).into_bytes(),
+        };
+
+        inspect_bytes(peer, Direction::Sent, &response);
+
+        // Only this peer is affected, e.g. an unreachable spoofed address
+        if let Err(err) = socket.send_to(&response, peer).await {
+            println!("Failed sending datagram to {peer}: {err}");
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> io::Result<()> {
-    default_tcp_listen(handle_client).await
+async fn main() -> anyhow::Result<()> {
+    let services = match env::var(SERVICES_ENV_VAR) {
+        Ok(spec) => parse_services(&spec)?,
+        Err(_) => vec![(Service::Echo, DEFAULT_PORT)],
+    };
+    let echo_mode = match env::var(ECHO_MODE_ENV_VAR) {
+        Ok(mode) => mode.parse()?,
+        Err(_) => EchoMode::default(),
+    };
+
+    let servers = services.into_iter().flat_map(|(service, port)| {
+        println!("Serving {service} on port {port}");
+
+        [
+            tokio::spawn(tcp_listen_on_port(port, move |stream, ctx| {
+                handle_client(service, echo_mode, stream, ctx)
+            })),
+            tokio::spawn(serve_udp(service, port)),
+        ]
+    });
+
+    // A server failing, e.g. on a port already in use, stops them all
+    try_join_all(servers.map(|server| async move { Ok::<_, anyhow::Error>(server.await??) }))
+        .await?;
+
+    Ok(())
 }
@@ -0,0 +1,261 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    num::ParseIntError,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+/// Printable ASCII characters, rotated through by chargen.
+const CHARGEN_CHARACTERS: &[u8] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+const CHARGEN_LINE_LENGTH: usize = 72;
+
+/// Seconds between 1900-01-01 (the RFC 868 epoch) and 1970-01-01.
+const TIME_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+const QUOTES: &[&str] = &[
+    "Premature optimization is the root of all evil. - Donald Knuth",
+    "Talk is cheap. Show me the code. - Linus Torvalds",
+    "Simplicity is prerequisite for reliability. - Edsger W. Dijkstra",
+    "Be conservative in what you do, be liberal in what you accept from others. - Jon Postel",
+];
+
+/// Starting from 1970-01-01, a Thursday.
+const WEEKDAYS: [&str; 7] = [
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// The RFC "simple services", each served over both TCP and UDP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+    /// RFC 862
+    Echo,
+    /// RFC 863
+    Discard,
+    /// RFC 864
+    Chargen,
+    /// RFC 867
+    Daytime,
+    /// RFC 868
+    Time,
+    /// RFC 865
+    Qotd,
+}
+
+impl FromStr for Service {
+    type Err = ServiceSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "echo" => Self::Echo,
+            "discard" => Self::Discard,
+            "chargen" => Self::Chargen,
+            "daytime" => Self::Daytime,
+            "time" => Self::Time,
+            "qotd" => Self::Qotd,
+            _ => return Err(ServiceSpecError::UnknownService(s.to_owned())),
+        })
+    }
+}
+
+impl Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Echo => "echo",
+            Self::Discard => "discard",
+            Self::Chargen => "chargen",
+            Self::Daytime => "daytime",
+            Self::Time => "time",
+            Self::Qotd => "qotd",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ServiceSpecError {
+    #[error("unknown service {0:?}")]
+    UnknownService(String),
+    #[error("expected service=port, got {0:?}")]
+    MissingPort(String),
+    #[error("invalid port: {0}")]
+    InvalidPort(#[from] ParseIntError),
+    #[error("port {0} is used more than once")]
+    DuplicatePort(u16),
+}
+
+/// Parses comma-separated `service=port` pairs, e.g. `echo=7,daytime=13`.
+pub fn parse_services(spec: &str) -> Result<Vec<(Service, u16)>, ServiceSpecError> {
+    let mut ports = HashSet::new();
+
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (service, port) = entry
+                .split_once('=')
+                .ok_or_else(|| ServiceSpecError::MissingPort(entry.to_owned()))?;
+            let port = port.trim().parse::<u16>()?;
+
+            if !ports.insert(port) {
+                return Err(ServiceSpecError::DuplicatePort(port));
+            }
+
+            Ok((service.trim().parse()?, port))
+        })
+        .collect()
+}
+
+/// The rotating chargen pattern, one CRLF terminated line at a time.
+#[derive(Debug, Default)]
+pub struct Chargen {
+    offset: usize,
+}
+
+impl Chargen {
+    pub fn next_line(&mut self) -> Vec<u8> {
+        let mut line = CHARGEN_CHARACTERS
+            .iter()
+            .cycle()
+            .skip(self.offset)
+            .take(CHARGEN_LINE_LENGTH)
+            .copied()
+            .collect::<Vec<_>>();
+        line.extend_from_slice(b"\r\n");
+
+        self.offset = (self.offset + 1) % CHARGEN_CHARACTERS.len();
+
+        line
+    }
+}
+
+fn unix_seconds(now: SystemTime) -> u64 {
+    now.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// The date and time as suggested by RFC 867, e.g. `Monday, February 22, 1982 17:37:43-UTC`.
+pub fn daytime(now: SystemTime) -> String {
+    let seconds = unix_seconds(now);
+    let days = seconds / 86_400;
+    let (year, month, day) = civil_from_days(days);
+    let time = seconds % 86_400;
+
+    format!(
+        "{}, {} {day}, {year} {:02}:{:02}:{:02}-UTC\r\n",
+        WEEKDAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1],
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Seconds since 1900-01-01 as RFC 868 32-bit big-endian, wrapping in 2036.
+pub fn time(now: SystemTime) -> [u8; 4] {
+    ((unix_seconds(now) + TIME_EPOCH_OFFSET) as u32).to_be_bytes()
+}
+
+/// A different quote every day.
+pub fn quote(now: SystemTime) -> String {
+    let days = unix_seconds(now) / 86_400;
+
+    format!("{}\r\n", QUOTES[days as usize % QUOTES.len()])
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date, see
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_services() {
+        assert_eq!(
+            parse_services("echo=7, chargen=19,").unwrap(),
+            [(Service::Echo, 7), (Service::Chargen, 19)]
+        );
+
+        assert!(matches!(
+            parse_services("echo=7,time=7"),
+            Err(ServiceSpecError::DuplicatePort(7))
+        ));
+        assert!(matches!(
+            parse_services("ftp=21"),
+            Err(ServiceSpecError::UnknownService(_))
+        ));
+        assert!(matches!(
+            parse_services("echo"),
+            Err(ServiceSpecError::MissingPort(_))
+        ));
+    }
+
+    #[test]
+    fn test_chargen() {
+        let mut chargen = Chargen::default();
+
+        // The first lines from RFC 864
+        assert_eq!(
+            chargen.next_line(),
+            b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefg\r\n"
+        );
+        assert_eq!(
+            chargen.next_line(),
+            b"!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefgh\r\n"
+        );
+    }
+
+    #[test]
+    fn test_clock() {
+        // The example from RFC 867, in UTC
+        let now = UNIX_EPOCH + Duration::from_secs(383_247_463);
+        assert_eq!(daytime(now), "Monday, February 22, 1982 17:37:43-UTC\r\n");
+
+        // The examples from RFC 868
+        assert_eq!(time(UNIX_EPOCH), 2_208_988_800u32.to_be_bytes());
+        let now = UNIX_EPOCH + Duration::from_secs(2_629_584_000 - TIME_EPOCH_OFFSET);
+        assert_eq!(time(now), 2_629_584_000u32.to_be_bytes());
+    }
+}
@@ -0,0 +1,50 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use num_bigint::BigUint;
+use protohackers_1_prime_time::{primality, primes::Primes};
+use std::num::NonZeroUsize;
+
+const SIEVE_LIMIT: u64 = 1 << 24;
+
+fn primes() -> Primes {
+    Primes::new(SIEVE_LIMIT, NonZeroUsize::new(4096).unwrap())
+}
+
+/// Small numbers as the checker sends them, repeating and below the sieve limit.
+fn small_numbers(c: &mut Criterion) {
+    let numbers = (0..1024u64)
+        .map(|i| BigUint::from(i * 7919 % SIEVE_LIMIT))
+        .collect::<Vec<_>>();
+    let primes = primes();
+
+    let mut group = c.benchmark_group("small_numbers");
+    group.bench_function("from_scratch", |b| {
+        b.iter(|| numbers.iter().filter(|n| primality::is_prime(n)).count())
+    });
+    group.bench_function("sieve", |b| {
+        b.iter(|| numbers.iter().filter(|n| primes.is_prime(n)).count())
+    });
+    group.finish();
+}
+
+/// The same large prime over and over, which the LRU remembers.
+fn repeated_large_number(c: &mut Criterion) {
+    let primes = primes();
+    let mut group = c.benchmark_group("repeated_large_number");
+
+    for bits in [127u32, 521, 2203] {
+        // Mersenne primes, which trial division doesn't rule out
+        let n = (BigUint::from(1u32) << bits) - 1u32;
+
+        group.bench_with_input(BenchmarkId::new("from_scratch", bits), &n, |b, n| {
+            b.iter(|| primality::is_prime(black_box(n)))
+        });
+        group.bench_with_input(BenchmarkId::new("cached", bits), &n, |b, n| {
+            b.iter(|| primes.is_prime(black_box(n)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, small_numbers, repeated_large_number);
+criterion_main!(benches);
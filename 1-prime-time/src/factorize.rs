@@ -0,0 +1,140 @@
+use crate::primality::is_prime;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+/// Divisors tried before Pollard's rho, which is slow to find small factors.
+const TRIAL_DIVISION_LIMIT: u32 = 1000;
+
+/// Rho steps between gcds.
+const RHO_BATCH: u64 = 128;
+
+/// Prime factors of `n` in ascending order, repeated by multiplicity, giving up with `None` after
+/// `max_iterations` steps of Pollard's rho.
+pub fn factorize(n: &BigUint, max_iterations: u64) -> Option<Vec<BigUint>> {
+    let mut factors = Vec::new();
+    let mut n = n.clone();
+
+    if n.is_zero() {
+        return Some(factors);
+    }
+
+    for divisor in 2..TRIAL_DIVISION_LIMIT {
+        while (&n % divisor).is_zero() {
+            factors.push(divisor.into());
+            n /= divisor;
+        }
+    }
+
+    let mut budget = max_iterations;
+    let mut composites = vec![n];
+
+    while let Some(n) = composites.pop() {
+        if n.is_one() {
+            continue;
+        }
+        if is_prime(&n) {
+            factors.push(n);
+            continue;
+        }
+
+        let divisor = pollard_brent(&n, &mut budget)?;
+        composites.push(&n / &divisor);
+        composites.push(divisor);
+    }
+
+    factors.sort();
+
+    Some(factors)
+}
+
+/// A non-trivial divisor of the composite `n`, using Brent's variant of Pollard's rho.
+fn pollard_brent(n: &BigUint, budget: &mut u64) -> Option<BigUint> {
+    let distance = |a: &BigUint, b: &BigUint| if a > b { a - b } else { b - a };
+    let mut c = 1u32;
+
+    loop {
+        let step = |x: &BigUint| (x * x + c) % n;
+
+        let mut x;
+        let mut y = BigUint::from(2u32);
+        let mut saved_y = y.clone();
+        let mut product = BigUint::one();
+        let mut divisor = BigUint::one();
+        let mut cycle_length = 1;
+
+        while divisor.is_one() {
+            x = y.clone();
+            *budget = budget.checked_sub(cycle_length)?;
+            for _ in 0..cycle_length {
+                y = step(&y);
+            }
+
+            let mut steps = 0;
+            while steps < cycle_length && divisor.is_one() {
+                saved_y = y.clone();
+                let batch = RHO_BATCH.min(cycle_length - steps);
+                *budget = budget.checked_sub(batch)?;
+                for _ in 0..batch {
+                    y = step(&y);
+                    product = product * distance(&x, &y) % n;
+                }
+                divisor = product.gcd(n);
+                steps += batch;
+            }
+
+            if divisor == *n {
+                // The batch overshot, so walk it again one gcd at a time
+                loop {
+                    saved_y = step(&saved_y);
+                    divisor = distance(&x, &saved_y).gcd(n);
+                    if !divisor.is_one() {
+                        break;
+                    }
+                }
+            }
+
+            cycle_length *= 2;
+        }
+
+        if divisor != *n {
+            return Some(divisor);
+        }
+
+        // Both factors cycled together, try another polynomial
+        c += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factorize() {
+        let factorize = |n: u128| {
+            factorize(&n.into(), 1 << 20).map(|factors| {
+                factors
+                    .into_iter()
+                    .map(|factor| factor.try_into().unwrap())
+                    .collect::<Vec<u128>>()
+            })
+        };
+
+        assert_eq!(factorize(0), Some(vec![]));
+        assert_eq!(factorize(1), Some(vec![]));
+        assert_eq!(factorize(360), Some(vec![2, 2, 2, 3, 3, 5]));
+        assert_eq!(
+            factorize(4_294_967_291 * 4_294_967_279),
+            Some(vec![4_294_967_279, 4_294_967_291])
+        );
+        assert_eq!(
+            factorize(1009 * 1009 * 65_537 * 2_147_483_647),
+            Some(vec![1009, 1009, 65_537, 2_147_483_647])
+        );
+
+        // Finding a 31 bit factor takes tens of thousands of steps
+        let n = BigUint::from(2_147_483_647u128 * ((1 << 61) - 1));
+        assert_eq!(super::factorize(&n, 1000), None);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod factorize;
+pub mod method;
+pub mod number;
+pub mod primality;
+pub mod primes;
+pub mod request;
+pub mod response;
+pub mod rpc;
+pub mod sieve;
@@ -1,48 +1,131 @@
-mod request;
-mod response;
+use futures::SinkExt;
+use protohackers_1_prime_time::{
+    method,
+    primes::Primes,
+    request::Request,
+    response::Response,
+    rpc::{self, RpcOutput},
+};
+use protohackers_utils::{
+    default_tcp_listen, framed_json, serve_pipelined, ConnectionContext, ConnectionStream,
+    InspectCodec, JsonFrame, PipelineConfig, ServeError,
+};
+use std::{env, num::NonZeroUsize, sync::Arc};
+use tokio::task;
+use tower::service_fn;
 
-use futures::{SinkExt, StreamExt};
-use protohackers_utils::{default_tcp_listen, framed_json};
-use request::Request;
-use response::Response;
-use std::net::SocketAddr;
-use tokio::net::TcpStream;
+/// Numbers up to this are looked up in a sieve built at startup, one bit per odd number.
+const SIEVE_LIMIT_ENV_VAR: &str = "PRIME_TIME_SIEVE_LIMIT";
+const DEFAULT_SIEVE_LIMIT: u64 = 1 << 24;
 
-async fn handle_client(stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
-    let mut framed = framed_json::<_, Request, Response>(stream);
+/// `protohackers` (the default) or `jsonrpc`, for JSON-RPC 2.0 envelopes and errors.
+const PROTOCOL_ENV_VAR: &str = "PRIME_TIME_PROTOCOL";
 
-    while let Some(item) = framed.next().await {
-        #[cfg(debug_assertions)]
-        println!("{addr} --> {item:?}");
+/// Large numbers whose primality is remembered.
+const CACHE_CAPACITY: NonZeroUsize = match NonZeroUsize::new(4096) {
+    Some(capacity) => capacity,
+    None => unreachable!(),
+};
 
-        match item {
-            Ok(Request::IsPrime { number }) => {
-                let response = Response::is_prime(number.as_u64().map_or(false, primes::is_prime));
+async fn handle_request(
+    request: Request,
+    primes: Arc<Primes>,
+) -> Result<Option<Response>, task::JoinError> {
+    // Number theory on big numbers can take a while, keep it off the runtime, within its budget
+    let response = task::spawn_blocking(move || {
+        method::call(request, &primes).unwrap_or_else(Response::error_with_reason)
+    })
+    .await?;
 
-                #[cfg(debug_assertions)]
-                println!("{addr} <-- {response:?}");
+    Ok(Some(response))
+}
 
-                framed.send(response).await?;
-            }
-            Err(err) => {
-                let response = Response::error();
+async fn handle_client(
+    stream: ConnectionStream,
+    ctx: ConnectionContext,
+    primes: Arc<Primes>,
+) -> anyhow::Result<()> {
+    let mut framed = framed_json::<_, Request, Response>(stream)
+        .map_codec(|codec| InspectCodec::new(codec, ctx));
 
-                #[cfg(debug_assertions)]
-                println!("{addr} <-- {response:?}");
+    let result = serve_pipelined(
+        &mut framed,
+        service_fn(|request| handle_request(request, Arc::clone(&primes))),
+        PipelineConfig::default(),
+    )
+    .await;
 
-                framed.send(response).await?;
+    match result {
+        Ok(()) => Ok(()),
+        Err(ServeError::Decode(err)) => {
+            framed.send(Response::error()).await?;
 
-                return Err(err.into());
-            }
-        };
+            Err(err.into())
+        }
+        Err(err) => Err(err.into()),
     }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Protocol {
+    Protohackers,
+    JsonRpc,
+}
+
+async fn handle_rpc_frame(
+    frame: JsonFrame,
+    primes: Arc<Primes>,
+) -> Result<Option<RpcOutput>, task::JoinError> {
+    task::spawn_blocking(move || rpc::handle(frame.as_bytes(), &primes, rpc::LINE_BUDGET)).await
+}
+
+/// Unlike [`handle_client`], lines are decoded by [`rpc::handle`], which answers every error and
+/// keeps the connection open.
+async fn handle_rpc_client(
+    stream: ConnectionStream,
+    ctx: ConnectionContext,
+    primes: Arc<Primes>,
+) -> anyhow::Result<()> {
+    let framed = framed_json::<_, JsonFrame, RpcOutput>(stream)
+        .map_codec(|codec| InspectCodec::new(codec, ctx));
+
+    serve_pipelined(
+        framed,
+        service_fn(|frame| handle_rpc_frame(frame, Arc::clone(&primes))),
+        PipelineConfig::default(),
+    )
+    .await?;
 
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    default_tcp_listen(handle_client).await?;
+    let protocol = match env::var(PROTOCOL_ENV_VAR).as_deref() {
+        Ok("protohackers") | Err(_) => Protocol::Protohackers,
+        Ok("jsonrpc") => Protocol::JsonRpc,
+        Ok(protocol) => {
+            anyhow::bail!("unknown protocol {protocol:?}, expected protohackers or jsonrpc")
+        }
+    };
+    let sieve_limit = match env::var(SIEVE_LIMIT_ENV_VAR) {
+        Ok(limit) => limit.parse()?,
+        Err(_) => DEFAULT_SIEVE_LIMIT,
+    };
+    let primes = task::spawn_blocking(move || Primes::new(sieve_limit, CACHE_CAPACITY)).await?;
+    let primes = Arc::new(primes);
+
+    default_tcp_listen(move |stream, ctx| {
+        let primes = Arc::clone(&primes);
+
+        async move {
+            match protocol {
+                Protocol::Protohackers => handle_client(stream, ctx, primes).await,
+                Protocol::JsonRpc => handle_rpc_client(stream, ctx, primes).await,
+            }
+        }
+    })
+    .await?;
 
     Ok(())
 }
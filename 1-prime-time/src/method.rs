@@ -0,0 +1,222 @@
+use crate::{
+    factorize::factorize,
+    number::{from_natural, to_natural, Number, NumberError},
+    primality::{next_prime, prev_prime},
+    primes::Primes,
+    request::Request,
+    response::Response,
+    sieve::Sieve,
+};
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use thiserror::Error;
+
+// CPU budgets per method, so that huge inputs get an error rather than tying up a thread
+
+/// Largest number tested by `isPrime`, unless trial division rules it out.
+pub const MAX_PRIMALITY_BITS: u64 = 4096;
+
+pub const MAX_BATCH_LEN: usize = 1024;
+
+/// Total size of the numbers in an `isPrimeBatch`.
+pub const MAX_BATCH_BITS: u64 = 64 * 1024;
+
+/// Largest number searched from by `nextPrime` and `prevPrime`.
+pub const MAX_SEARCH_BITS: u64 = 1024;
+
+/// Candidates tried by `nextPrime` and `prevPrime`, far more than any prime gap below
+/// [`MAX_SEARCH_BITS`].
+pub const MAX_SEARCH_CANDIDATES: u64 = 100_000;
+
+pub const MAX_FACTORIZE_BITS: u64 = 512;
+
+/// Pollard's rho steps spent by `factorize`, finding factors up to about 40 bits.
+pub const MAX_RHO_ITERATIONS: u64 = 1 << 20;
+
+/// Largest `n` counted up to by `primeCount`, sieving past the shared sieve if needed.
+pub const MAX_PRIME_COUNT: u64 = 10_000_000;
+
+#[derive(Debug, Error)]
+pub enum MethodError {
+    #[error("number is {0}")]
+    Number(#[from] NumberError),
+    #[error("{0} has no prime factorization")]
+    NoFactorization(u8),
+    #[error("there is no prime below 3")]
+    NoPrevPrime,
+    #[error("number larger than {0} bits")]
+    TooManyBits(u64),
+    #[error("number larger than {0}")]
+    TooLarge(u64),
+    #[error("more than {0} numbers")]
+    TooManyNumbers(usize),
+    #[error("over the CPU budget")]
+    OverBudget,
+}
+
+pub fn call(request: Request, primes: &Primes) -> Result<Response, MethodError> {
+    Ok(match request {
+        Request::IsPrime { number } => {
+            Response::is_prime(is_prime(primes, &number, MAX_PRIMALITY_BITS)?)
+        }
+        Request::Factorize { number } => {
+            let number = to_natural(&number)?;
+            if number.is_zero() {
+                return Err(MethodError::NoFactorization(0));
+            }
+            check_bits(&number, MAX_FACTORIZE_BITS)?;
+
+            let factors = factorize(&number, MAX_RHO_ITERATIONS).ok_or(MethodError::OverBudget)?;
+
+            Response::Factorize {
+                factors: factors.iter().map(from_natural).collect(),
+            }
+        }
+        Request::NextPrime { number } => {
+            let number = to_natural(&number)?;
+            check_bits(&number, MAX_SEARCH_BITS)?;
+
+            let prime = next_prime(&number, MAX_SEARCH_CANDIDATES, |candidate| {
+                primes.is_prime_uncached(candidate)
+            })
+            .ok_or(MethodError::OverBudget)?;
+
+            Response::NextPrime {
+                prime: from_natural(&prime),
+            }
+        }
+        Request::PrevPrime { number } => {
+            let number = to_natural(&number)?;
+            if number <= BigUint::from(2u32) {
+                return Err(MethodError::NoPrevPrime);
+            }
+            check_bits(&number, MAX_SEARCH_BITS)?;
+
+            let prime = prev_prime(&number, MAX_SEARCH_CANDIDATES, |candidate| {
+                primes.is_prime_uncached(candidate)
+            })
+            .ok_or(MethodError::OverBudget)?;
+
+            Response::PrevPrime {
+                prime: from_natural(&prime),
+            }
+        }
+        Request::PrimeCount { number } => {
+            let number = to_natural(&number)?
+                .to_u64()
+                .filter(|&number| number <= MAX_PRIME_COUNT)
+                .ok_or(MethodError::TooLarge(MAX_PRIME_COUNT))?;
+
+            let count = primes.sieve().prime_count(number).unwrap_or_else(|| {
+                Sieve::new(number)
+                    .prime_count(number)
+                    .expect("sieved up to number")
+            });
+
+            Response::PrimeCount { count }
+        }
+        Request::IsPrimeBatch { numbers } => {
+            if numbers.len() > MAX_BATCH_LEN {
+                return Err(MethodError::TooManyNumbers(MAX_BATCH_LEN));
+            }
+
+            // Each number may use the whole batch's budget, but not more than what's left of it
+            let mut budget = MAX_BATCH_BITS;
+            let primes = numbers
+                .iter()
+                .map(|number| {
+                    let bits = to_natural(number).map_or(0, |number| number.bits());
+                    budget = budget.checked_sub(bits).ok_or(MethodError::OverBudget)?;
+
+                    is_prime(primes, number, MAX_PRIMALITY_BITS)
+                })
+                .collect::<Result<_, _>>()?;
+
+            Response::IsPrimeBatch { primes }
+        }
+    })
+}
+
+/// Non-integers, negatives and numbers past [`crate::number::MAX_EXPONENT`] (multiples of ten)
+/// are simply not prime.
+fn is_prime(primes: &Primes, number: &Number, max_bits: u64) -> Result<bool, MethodError> {
+    match to_natural(number) {
+        Ok(number) => primes
+            .is_prime_within(&number, max_bits)
+            .ok_or(MethodError::TooManyBits(max_bits)),
+        Err(NumberError::TooManyDigits) => Err(MethodError::TooManyBits(max_bits)),
+        Err(_) => Ok(false),
+    }
+}
+
+fn check_bits(number: &BigUint, max_bits: u64) -> Result<(), MethodError> {
+    if number.bits() > max_bits {
+        return Err(MethodError::TooManyBits(max_bits));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroUsize;
+
+    fn call(request: &str) -> String {
+        // A small sieve, so that primeCount also sieves past it
+        let primes = Primes::new(50, NonZeroUsize::new(16).unwrap());
+        let response = match super::call(serde_json::from_str(request).unwrap(), &primes) {
+            Ok(response) => response,
+            Err(err) => Response::error_with_reason(err),
+        };
+
+        serde_json::to_string(&response).unwrap()
+    }
+
+    #[test]
+    fn test_methods() {
+        assert_eq!(
+            call(r#"{"method":"factorize","number":18446744073709551615}"#),
+            r#"{"method":"factorize","factors":[3,5,17,257,641,65537,6700417]}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"nextPrime","number":18446744073709551615}"#),
+            r#"{"method":"nextPrime","prime":18446744073709551629}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"prevPrime","number":100.0}"#),
+            r#"{"method":"prevPrime","prime":97}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"primeCount","number":100}"#),
+            r#"{"method":"primeCount","count":25}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"isPrimeBatch","numbers":[2,4,7.0,7.5,-7]}"#),
+            r#"{"method":"isPrimeBatch","primes":[true,false,true,false,false]}"#
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            call(r#"{"method":"factorize","number":7.5}"#),
+            r#"{"method":"error","reason":"number is not an integer"}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"prevPrime","number":2}"#),
+            r#"{"method":"error","reason":"there is no prime below 3"}"#
+        );
+        assert_eq!(
+            call(r#"{"method":"primeCount","number":1e12}"#),
+            r#"{"method":"error","reason":"number larger than 10000000"}"#
+        );
+
+        // 2^4423 - 1 is a Mersenne prime, so trial division can't rule it out
+        let mersenne = (BigUint::from(1u32) << 4423) - 1u32;
+        assert_eq!(
+            call(&format!(r#"{{"method":"isPrime","number":{mersenne}}}"#)),
+            r#"{"method":"error","reason":"number larger than 4096 bits"}"#
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{Pow, Zero};
+use serde::{Serialize, Serializer};
+use serde_json::value::RawValue;
+use thiserror::Error;
+
+/// Largest power of ten an integer gets expanded by, `1e1000000000` would take a gigabyte. Past
+/// this it's a multiple of ten anyway.
+pub const MAX_EXPONENT: u32 = 4096;
+
+/// Most significant digits parsed, about 66,000 bits and so past every method's budget. Parsing
+/// takes quadratic time, and lines have no length limit.
+pub const MAX_DIGITS: usize = 20_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NumberError {
+    #[error("not an integer")]
+    NotInteger,
+    #[error("exponent larger than {MAX_EXPONENT}")]
+    TooLarge,
+    #[error("more than {MAX_DIGITS} digits")]
+    TooManyDigits,
+    #[error("negative")]
+    Negative,
+}
+
+/// A JSON number token, as written so that big integers keep every digit.
+///
+/// Read from raw JSON rather than through serde_json's `arbitrary_precision`, which also takes
+/// `{"$serde_json::private::Number":"7"}` for a number, and applies to the whole workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number(String);
+
+impl Number {
+    /// `None` unless `raw` is a number token, strings holding one excluded.
+    pub fn from_raw(raw: &RawValue) -> Option<Self> {
+        // Valid JSON that starts like a number is one
+        let raw = raw.get();
+        raw.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+            .then(|| Self(raw.to_owned()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RawValue::from_string(self.0.clone())
+            .expect("numbers are valid JSON")
+            .serialize(serializer)
+    }
+}
+
+/// Converts a JSON number to the integer it represents, as written and so without any precision
+/// lost. Integral floats such as `7.0` or `70e-1` count as integers.
+pub fn to_integer(number: &Number) -> Result<BigInt, NumberError> {
+    let number = number.as_str();
+    let (negative, number) = match number.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, number),
+    };
+    let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, parse_exponent(exponent)),
+        None => (number, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{integer}{fraction}");
+    let significant = digits.trim_end_matches('0');
+    let exponent = exponent
+        .saturating_sub(fraction.len() as i64)
+        .saturating_add((digits.len() - significant.len()) as i64);
+    let significant = significant.trim_start_matches('0');
+
+    if significant.is_empty() {
+        return Ok(BigInt::zero());
+    }
+    if exponent < 0 {
+        return Err(NumberError::NotInteger);
+    }
+    if exponent > i64::from(MAX_EXPONENT) {
+        return Err(NumberError::TooLarge);
+    }
+    if significant.len() > MAX_DIGITS {
+        return Err(NumberError::TooManyDigits);
+    }
+
+    let significant = BigInt::parse_bytes(significant.as_bytes(), 10)
+        .expect("JSON numbers only have digits there");
+    let integer = significant * BigInt::from(10).pow(exponent as u32);
+
+    Ok(if negative { -integer } else { integer })
+}
+
+/// Like [`to_integer`], for non-negative integers only.
+pub fn to_natural(number: &Number) -> Result<BigUint, NumberError> {
+    to_integer(number)?
+        .to_biguint()
+        .ok_or(NumberError::Negative)
+}
+
+pub fn from_natural(natural: &BigUint) -> Number {
+    Number(natural.to_string())
+}
+
+/// Parses an exponent's digits, saturating since any exponent out of range means either a huge
+/// number or a fraction.
+fn parse_exponent(exponent: &str) -> i64 {
+    let (negative, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, exponent.trim_start_matches('+')),
+    };
+
+    match (digits.parse::<i64>(), negative) {
+        (Ok(exponent), true) => -exponent,
+        (Ok(exponent), false) => exponent,
+        (Err(_), true) => i64::MIN,
+        (Err(_), false) => i64::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_integer(number: &str) -> Result<BigInt, NumberError> {
+        let raw = RawValue::from_string(number.to_owned()).unwrap();
+        super::to_integer(&Number::from_raw(&raw).unwrap())
+    }
+
+    #[test]
+    fn test_to_integer() {
+        assert_eq!(to_integer("7"), Ok(BigInt::from(7)));
+        assert_eq!(to_integer("-7"), Ok(BigInt::from(-7)));
+        assert_eq!(to_integer("7.0"), Ok(BigInt::from(7)));
+        assert_eq!(to_integer("70e-1"), Ok(BigInt::from(7)));
+        assert_eq!(to_integer("0.07E+2"), Ok(BigInt::from(7)));
+        assert_eq!(to_integer("0.0e-99999999999999999999"), Ok(BigInt::zero()));
+        assert_eq!(
+            to_integer("18446744073709551629"),
+            Ok("18446744073709551629".parse().unwrap())
+        );
+        assert_eq!(to_integer("1e30"), Ok(BigInt::from(10).pow(30u32)));
+
+        assert_eq!(to_integer("7.5"), Err(NumberError::NotInteger));
+        assert_eq!(to_integer("7e-1"), Err(NumberError::NotInteger));
+        assert_eq!(
+            to_integer("1e99999999999999999999"),
+            Err(NumberError::TooLarge)
+        );
+        assert_eq!(
+            to_integer(&"7".repeat(MAX_DIGITS + 1)),
+            Err(NumberError::TooManyDigits)
+        );
+        // Only significant digits count
+        assert_eq!(
+            to_integer(&format!("7{}", "0".repeat(MAX_DIGITS))),
+            Err(NumberError::TooLarge)
+        );
+    }
+}
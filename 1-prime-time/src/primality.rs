@@ -0,0 +1,266 @@
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+/// Witnesses making Miller-Rabin deterministic below 2^64, also used for trial division.
+const SMALL_PRIMES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Deterministic below 2^64, and Baillie-PSW above, which has no known counterexample.
+pub fn is_prime(n: &BigUint) -> bool {
+    match n.to_u64() {
+        Some(n) => is_prime_u64(n),
+        None => is_bpsw_probable_prime(n),
+    }
+}
+
+/// Trial division by [`SMALL_PRIMES`], for `n` above them.
+pub fn has_small_factor(n: &BigUint) -> bool {
+    SMALL_PRIMES.iter().any(|&p| (n % p).is_zero())
+}
+
+/// The smallest prime above `n` according to `is_prime`, giving up with `None` after
+/// `max_candidates`.
+pub fn next_prime<F>(n: &BigUint, max_candidates: u64, is_prime: F) -> Option<BigUint>
+where
+    F: Fn(&BigUint) -> bool,
+{
+    let mut candidate = n.clone();
+
+    (0..max_candidates).find_map(|_| {
+        candidate += 1u32;
+        is_prime(&candidate).then(|| candidate.clone())
+    })
+}
+
+/// The largest prime below `n` according to `is_prime`, giving up with `None` after
+/// `max_candidates` or on reaching 2.
+pub fn prev_prime<F>(n: &BigUint, max_candidates: u64, is_prime: F) -> Option<BigUint>
+where
+    F: Fn(&BigUint) -> bool,
+{
+    let mut candidate = n.clone();
+
+    for _ in 0..max_candidates {
+        if candidate <= BigUint::from(2u32) {
+            return None;
+        }
+        candidate -= 1u32;
+        if is_prime(&candidate) {
+            return Some(candidate);
+        }
+    }
+
+    None
+}
+
+/// Miller-Rabin over [`SMALL_PRIMES`].
+pub fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = SMALL_PRIMES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    SMALL_PRIMES.iter().all(|&base| {
+        let mut x = pow_mod(base, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+
+        (1..s).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(modulus)) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1;
+    base %= modulus;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+/// Base 2 Miller-Rabin followed by a strong Lucas test, for odd `n` above [`SMALL_PRIMES`].
+fn is_bpsw_probable_prime(n: &BigUint) -> bool {
+    if has_small_factor(n) {
+        return false;
+    }
+
+    is_strong_probable_prime(n, &BigUint::from(2u32)) && is_strong_lucas_probable_prime(n)
+}
+
+fn is_strong_probable_prime(n: &BigUint, base: &BigUint) -> bool {
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s;
+
+    let mut x = base.modpow(&d, n);
+    if x.is_one() || x == n_minus_one {
+        return true;
+    }
+
+    (1..s).any(|_| {
+        x = &x * &x % n;
+        x == n_minus_one
+    })
+}
+
+/// Strong Lucas test with Selfridge's parameters, as in FIPS 186-4 C.3.3.
+fn is_strong_lucas_probable_prime(n: &BigUint) -> bool {
+    // There would be no D with a Jacobi symbol of -1
+    if (n.sqrt().pow(2)) == *n {
+        return false;
+    }
+
+    // D in 5, -7, 9, -11... and Q = (1 - D) / 4, with P = 1
+    let to_residue = |x: i64| {
+        let magnitude = BigUint::from(x.unsigned_abs()) % n;
+        if x < 0 && !magnitude.is_zero() {
+            n - magnitude
+        } else {
+            magnitude
+        }
+    };
+    let mut d = 5i64;
+    let d = loop {
+        match jacobi(&to_residue(d), n) {
+            -1 => break d,
+            // Shares a factor with |D|, which is smaller than n
+            0 => return false,
+            _ => d = if d > 0 { -(d + 2) } else { -(d - 2) },
+        }
+    };
+    let q = to_residue((1 - d) / 4);
+    let d = to_residue(d);
+
+    let sub_mod = |a: BigUint, b: BigUint| if a >= b { a - b } else { a + n - b };
+    let half_mod = |x: BigUint| {
+        let x = x % n;
+        if x.is_odd() {
+            (x + n) >> 1
+        } else {
+            x >> 1
+        }
+    };
+
+    let n_plus_one = n + 1u32;
+    let s = n_plus_one.trailing_zeros().unwrap_or(0);
+    let k = &n_plus_one >> s;
+
+    let mut u = BigUint::one();
+    let mut v = BigUint::one();
+    let mut q_k = q.clone();
+
+    for bit in (0..k.bits() - 1).rev() {
+        u = &u * &v % n;
+        v = sub_mod(&v * &v % n, &q_k * 2u32 % n);
+        q_k = &q_k * &q_k % n;
+
+        if k.bit(bit) {
+            (u, v) = (half_mod(&u + &v), half_mod(&d * &u + &v));
+            q_k = &q_k * &q % n;
+        }
+    }
+
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+
+    (1..s).any(|_| {
+        v = sub_mod(&v * &v % n, &q_k * 2u32 % n);
+        q_k = &q_k * &q_k % n;
+        v.is_zero()
+    })
+}
+
+/// The Jacobi symbol (a/n) for odd n.
+fn jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    let low_bits = |x: &BigUint| x.iter_u64_digits().next().unwrap_or(0);
+
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+
+    while !a.is_zero() {
+        let twos = a.trailing_zeros().unwrap_or(0);
+        a >>= twos;
+        if twos % 2 == 1 && matches!(low_bits(&n) % 8, 3 | 5) {
+            result = -result;
+        }
+
+        std::mem::swap(&mut a, &mut n);
+        if low_bits(&a) % 4 == 3 && low_bits(&n) % 4 == 3 {
+            result = -result;
+        }
+        a %= &n;
+    }
+
+    if n.is_one() {
+        result
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_prime_naive(n: u64) -> bool {
+        n >= 2
+            && (2..)
+                .take_while(|d| d * d <= n)
+                .all(|d| !n.is_multiple_of(d))
+    }
+
+    #[test]
+    fn test_small_numbers() {
+        for n in 0..20_000 {
+            assert_eq!(is_prime_u64(n), is_prime_naive(n), "{n}");
+            // Also go through BPSW, which is otherwise only used above 2^64
+            if n > 37 && n % 2 == 1 {
+                assert_eq!(is_bpsw_probable_prime(&n.into()), is_prime_naive(n), "{n}");
+            }
+        }
+
+        // Strong Lucas pseudoprimes, only caught by the base 2 Miller-Rabin part of BPSW
+        for n in [5459u32, 5777, 10877, 16109, 18971] {
+            assert!(is_strong_lucas_probable_prime(&n.into()), "{n}");
+        }
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let is_prime = |n: &str| is_prime(&n.parse().unwrap());
+
+        // Strong pseudoprimes to all bases up to 23 and 37 respectively
+        assert!(!is_prime_u64(3_825_123_056_546_413_051));
+        assert!(!is_prime("318665857834031151167461"));
+
+        // The first prime above 2^64, and Mersenne primes 2^89 - 1 and 2^127 - 1
+        assert!(is_prime("18446744073709551629"));
+        assert!(is_prime("618970019642690137449562111"));
+        assert!(is_prime("170141183460469231731687303715884105727"));
+
+        // 2^128 + 1 and (2^61 - 1)^2
+        assert!(!is_prime("340282366920938463463374607431768211457"));
+        assert!(!is_prime("5316911983139663487003542222693990401"));
+    }
+}
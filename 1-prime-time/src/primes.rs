@@ -0,0 +1,71 @@
+use crate::{
+    primality::{self, has_small_factor},
+    sieve::Sieve,
+};
+use lru::LruCache;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use std::{num::NonZeroUsize, sync::Mutex};
+
+/// Primality shared by all connections: a sieve for small numbers and an LRU of the results for
+/// large ones, which are the expensive ones to repeat.
+#[derive(Debug)]
+pub struct Primes {
+    sieve: Sieve,
+    cache: Mutex<LruCache<BigUint, bool>>,
+}
+
+impl Primes {
+    pub fn new(sieve_limit: u64, cache_capacity: NonZeroUsize) -> Self {
+        Self {
+            sieve: Sieve::new(sieve_limit),
+            cache: Mutex::new(LruCache::new(cache_capacity)),
+        }
+    }
+
+    pub fn sieve(&self) -> &Sieve {
+        &self.sieve
+    }
+
+    pub fn is_prime(&self, n: &BigUint) -> bool {
+        if let Some(is_prime) = self.sieve_lookup(n) {
+            return is_prime;
+        }
+        // Below 2^64 Miller-Rabin takes about as long as a lookup
+        if n.bits() <= 64 || has_small_factor(n) {
+            return primality::is_prime(n);
+        }
+
+        if let Some(&is_prime) = self.cache.lock().unwrap().get(n) {
+            return is_prime;
+        }
+
+        let is_prime = primality::is_prime(n);
+        self.cache.lock().unwrap().put(n.clone(), is_prime);
+
+        is_prime
+    }
+
+    /// Like [`Self::is_prime`], without going through the cache, e.g. for candidates that are not
+    /// worth remembering.
+    pub fn is_prime_uncached(&self, n: &BigUint) -> bool {
+        self.sieve_lookup(n)
+            .unwrap_or_else(|| primality::is_prime(n))
+    }
+
+    /// Like [`Self::is_prime`], giving up with `None` on numbers over `max_bits` that trial
+    /// division doesn't rule out.
+    pub fn is_prime_within(&self, n: &BigUint, max_bits: u64) -> Option<bool> {
+        if n.bits() <= max_bits {
+            Some(self.is_prime(n))
+        } else if has_small_factor(n) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn sieve_lookup(&self, n: &BigUint) -> Option<bool> {
+        self.sieve.is_prime(n.to_u64()?)
+    }
+}
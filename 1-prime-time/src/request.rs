@@ -1,7 +1,118 @@
+use crate::number::Number;
 use serde::Deserialize;
+use serde_json::value::RawValue;
+use thiserror::Error;
 
 #[derive(Debug, PartialEq, Eq, Deserialize)]
-#[serde(tag = "method", rename_all = "camelCase")]
+#[serde(try_from = "RawRequest")]
 pub enum Request {
-    IsPrime { number: serde_json::Number },
+    IsPrime {
+        number: Number,
+    },
+    Factorize {
+        number: Number,
+    },
+    NextPrime {
+        number: Number,
+    },
+    PrevPrime {
+        number: Number,
+    },
+    /// The number of primes up to `number`.
+    PrimeCount {
+        number: Number,
+    },
+    IsPrimeBatch {
+        numbers: Vec<Number>,
+    },
+}
+
+/// A request with its numbers still raw JSON, which serde can't buffer for a tagged enum.
+#[derive(Debug, Deserialize)]
+pub struct RawRequest {
+    pub method: String,
+    pub number: Option<Box<RawValue>>,
+    pub numbers: Option<Box<RawValue>>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RequestError {
+    #[error("unknown method `{0}`")]
+    UnknownMethod(String),
+    #[error("missing field `{0}`")]
+    MissingField(&'static str),
+    #[error("`number` is not a number")]
+    NotNumber,
+    #[error("`numbers` is not an array of numbers")]
+    NotNumbers,
+}
+
+impl TryFrom<RawRequest> for Request {
+    type Error = RequestError;
+
+    fn try_from(raw: RawRequest) -> Result<Self, Self::Error> {
+        let number = || {
+            let number = raw
+                .number
+                .as_deref()
+                .ok_or(RequestError::MissingField("number"))?;
+            Number::from_raw(number).ok_or(RequestError::NotNumber)
+        };
+
+        Ok(match raw.method.as_str() {
+            "isPrime" => Self::IsPrime { number: number()? },
+            "factorize" => Self::Factorize { number: number()? },
+            "nextPrime" => Self::NextPrime { number: number()? },
+            "prevPrime" => Self::PrevPrime { number: number()? },
+            "primeCount" => Self::PrimeCount { number: number()? },
+            "isPrimeBatch" => {
+                let numbers = raw
+                    .numbers
+                    .as_deref()
+                    .ok_or(RequestError::MissingField("numbers"))?;
+                let numbers = serde_json::from_str::<Vec<&RawValue>>(numbers.get())
+                    .map_err(|_| RequestError::NotNumbers)?;
+
+                Self::IsPrimeBatch {
+                    numbers: numbers
+                        .into_iter()
+                        .map(Number::from_raw)
+                        .collect::<Option<_>>()
+                        .ok_or(RequestError::NotNumbers)?,
+                }
+            }
+            method => return Err(RequestError::UnknownMethod(method.to_owned())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers() {
+        let request = serde_json::from_str::<Request>(
+            r#"{"method":"isPrimeBatch","numbers":[18446744073709551629, 7.0]}"#,
+        )
+        .unwrap();
+        let Request::IsPrimeBatch { numbers } = request else {
+            panic!("unexpected request {request:?}");
+        };
+        assert_eq!(numbers[0].as_str(), "18446744073709551629");
+        assert_eq!(numbers[1].as_str(), "7.0");
+
+        for request in [
+            r#"{"method":"isPrime","number":"7"}"#,
+            r#"{"method":"isPrime","number":{"$serde_json::private::Number":"7"}}"#,
+            r#"{"method":"isPrimeBatch","numbers":[7,"7"]}"#,
+            r#"{"method":"isPrime"}"#,
+            r#"{"method":"isPrim","number":7}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<Request>(request).is_err(),
+                "{request}"
+            );
+        }
+    }
 }
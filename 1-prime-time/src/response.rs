@@ -1,10 +1,33 @@
+use crate::number::Number;
 use serde::Serialize;
+use std::fmt::Display;
 
 #[derive(Debug, PartialEq, Eq, Serialize)]
 #[serde(tag = "method", rename_all = "camelCase")]
 pub enum Response {
-    IsPrime { prime: bool },
-    Error,
+    IsPrime {
+        prime: bool,
+    },
+    Factorize {
+        factors: Vec<Number>,
+    },
+    NextPrime {
+        prime: Number,
+    },
+    PrevPrime {
+        prime: Number,
+    },
+    PrimeCount {
+        count: u64,
+    },
+    IsPrimeBatch {
+        primes: Vec<bool>,
+    },
+    /// Without a reason for malformed requests, which also end the connection.
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
 }
 
 impl Response {
@@ -13,7 +36,13 @@ impl Response {
     }
 
     pub fn error() -> Self {
-        Self::Error
+        Self::Error { reason: None }
+    }
+
+    pub fn error_with_reason(reason: impl Display) -> Self {
+        Self::Error {
+            reason: Some(reason.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,424 @@
+//! JSON-RPC 2.0 flavour of the protocol, for clients that prefer standard envelopes and errors
+//! over the disconnect-on-malformed policy.
+
+use crate::{
+    method::{self, MethodError},
+    number::NumberError,
+    primes::Primes,
+    request::{RawRequest, Request},
+    response::Response,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{
+    value::{to_raw_value, RawValue},
+    Value,
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// Implementation-defined server error, for calls over a method's CPU budget.
+pub const OVER_BUDGET: i64 = -32000;
+
+pub const MAX_BATCH_LEN: usize = 1024;
+
+/// Time a line's calls may start in. Each method has its own budget, but a batch of calls at the
+/// edge of theirs would otherwise tie up a thread for minutes.
+pub const LINE_BUDGET: Duration = Duration::from_secs(2);
+
+/// Methods and the name of their single parameter, which can also be passed by position.
+const METHODS: [(&str, &str); 6] = [
+    ("isPrime", "number"),
+    ("factorize", "number"),
+    ("nextPrime", "number"),
+    ("prevPrime", "number"),
+    ("primeCount", "number"),
+    ("isPrimeBatch", "numbers"),
+];
+
+/// Calls are read as raw JSON throughout, a [`Value`] would round numbers in params.
+#[derive(Debug, Deserialize)]
+struct RpcRequest<'a> {
+    jsonrpc: String,
+    method: String,
+    #[serde(borrow)]
+    params: Option<&'a RawValue>,
+    /// Absent for notifications, which is not the same as `null`.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    id: Option<Value>,
+}
+
+/// Just the id of a call, which may be invalid otherwise.
+#[derive(Debug, Deserialize)]
+struct RpcId {
+    id: Option<Value>,
+}
+
+fn deserialize_present<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    /// One of the standard errors, with its standard message and `detail` as data.
+    fn standard(code: i64, detail: impl Display) -> Self {
+        let message = match code {
+            PARSE_ERROR => "Parse error",
+            INVALID_REQUEST => "Invalid Request",
+            METHOD_NOT_FOUND => "Method not found",
+            INVALID_PARAMS => "Invalid params",
+            _ => "Internal error",
+        };
+
+        Self {
+            code,
+            message: message.to_owned(),
+            data: Some(detail.to_string()),
+        }
+    }
+}
+
+impl From<MethodError> for RpcError {
+    fn from(err: MethodError) -> Self {
+        match err {
+            MethodError::Number(NumberError::TooManyDigits) => Self::new(OVER_BUDGET, err),
+            MethodError::Number(_) | MethodError::NoFactorization(_) | MethodError::NoPrevPrime => {
+                Self::standard(INVALID_PARAMS, err)
+            }
+            MethodError::TooManyBits(_)
+            | MethodError::TooLarge(_)
+            | MethodError::TooManyNumbers(_)
+            | MethodError::OverBudget => Self::new(OVER_BUDGET, err),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Box<RawValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, outcome: Result<Box<RawValue>, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(err) => (None, Some(err)),
+        };
+
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// What's sent back for a received line, unless it only held notifications.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum RpcOutput {
+    Single(RpcResponse),
+    Batch(Vec<RpcResponse>),
+}
+
+impl AsRef<RpcOutput> for RpcOutput {
+    fn as_ref(&self) -> &RpcOutput {
+        self
+    }
+}
+
+/// Handles a received line, a single call or a batch of them. Every error is answered, so the
+/// connection can go on.
+///
+/// Calls of a batch after `budget` are answered with [`OVER_BUDGET`] without running, the first
+/// one always runs.
+pub fn handle(line: &[u8], primes: &Primes, budget: Duration) -> Option<RpcOutput> {
+    let start = Instant::now();
+
+    let single_error = |code, detail: &dyn Display| {
+        Some(RpcOutput::Single(RpcResponse::new(
+            Value::Null,
+            Err(RpcError::standard(code, detail)),
+        )))
+    };
+
+    let line = match serde_json::from_slice::<&RawValue>(line) {
+        Ok(line) => line,
+        Err(err) => return single_error(PARSE_ERROR, &err),
+    };
+
+    if !line.get().starts_with('[') {
+        return handle_call(line, primes, false).map(RpcOutput::Single);
+    }
+
+    match serde_json::from_str::<Vec<&RawValue>>(line.get()).expect("the line is an array") {
+        calls if calls.is_empty() => single_error(INVALID_REQUEST, &"empty batch"),
+        calls if calls.len() > MAX_BATCH_LEN => single_error(
+            INVALID_REQUEST,
+            &format_args!("batch larger than {MAX_BATCH_LEN}"),
+        ),
+        calls => {
+            let responses = calls
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, call)| {
+                    let over_budget = i > 0 && start.elapsed() >= budget;
+                    handle_call(call, primes, over_budget)
+                })
+                .collect::<Vec<_>>();
+
+            (!responses.is_empty()).then_some(RpcOutput::Batch(responses))
+        }
+    }
+}
+
+fn handle_call(call: &RawValue, primes: &Primes, over_budget: bool) -> Option<RpcResponse> {
+    // Worth answering an invalid request with its id, if it has a valid one
+    let id = match serde_json::from_str::<RpcId>(call.get()) {
+        Ok(RpcId {
+            id: Some(id @ (Value::Number(_) | Value::String(_))),
+        }) => id,
+        _ => Value::Null,
+    };
+    let invalid_request = |detail: &dyn Display| {
+        Some(RpcResponse::new(
+            id.clone(),
+            Err(RpcError::standard(INVALID_REQUEST, detail)),
+        ))
+    };
+
+    let request = match serde_json::from_str::<RpcRequest>(call.get()) {
+        Ok(request) => request,
+        Err(err) => return invalid_request(&err),
+    };
+    if request.jsonrpc != "2.0" {
+        return invalid_request(&"jsonrpc must be \"2.0\"");
+    }
+    if let Some(Value::Array(_) | Value::Object(_) | Value::Bool(_)) = request.id {
+        return invalid_request(&"id must be a string, a number or null");
+    }
+    if let Some(params) = request.params {
+        if !params.get().starts_with(['[', '{']) {
+            return invalid_request(&"params must be an array or an object");
+        }
+    }
+
+    let outcome = call_method(&request.method, request.params, primes, over_budget);
+
+    Some(RpcResponse::new(request.id?, outcome))
+}
+
+fn call_method(
+    method: &str,
+    params: Option<&RawValue>,
+    primes: &Primes,
+    over_budget: bool,
+) -> Result<Box<RawValue>, RpcError> {
+    let Some(&(_, param)) = METHODS.iter().find(|(name, _)| *name == method) else {
+        return Err(RpcError::standard(METHOD_NOT_FOUND, method));
+    };
+
+    let mut params = match params.map(RawValue::get) {
+        None => HashMap::new(),
+        Some(params) if params.starts_with('{') => {
+            serde_json::from_str(params).expect("params were checked to be an object")
+        }
+        Some(params) => match <[Box<RawValue>; 1]>::try_from(
+            serde_json::from_str::<Vec<_>>(params).expect("params were checked to be an array"),
+        ) {
+            Ok([value]) => HashMap::from([(param.to_owned(), value)]),
+            Err(params) => {
+                return Err(RpcError::standard(
+                    INVALID_PARAMS,
+                    format_args!("expected 1 parameter, got {}", params.len()),
+                ))
+            }
+        },
+    };
+
+    let request = Request::try_from(RawRequest {
+        method: method.to_owned(),
+        number: params.remove("number"),
+        numbers: params.remove("numbers"),
+    })
+    .map_err(|err| RpcError::standard(INVALID_PARAMS, err))?;
+
+    // Still validated, so that invalid calls get the same errors whatever the budget left
+    if over_budget {
+        return Err(MethodError::OverBudget.into());
+    }
+
+    let result = match method::call(request, primes)? {
+        Response::IsPrime { prime } => to_raw_value(&prime),
+        Response::Factorize { factors } => to_raw_value(&factors),
+        Response::NextPrime { prime } | Response::PrevPrime { prime } => to_raw_value(&prime),
+        Response::PrimeCount { count } => to_raw_value(&count),
+        Response::IsPrimeBatch { primes } => to_raw_value(&primes),
+        Response::Error { reason } => {
+            return Err(RpcError::standard(
+                INTERNAL_ERROR,
+                reason.unwrap_or_default(),
+            ))
+        }
+    };
+
+    Ok(result.expect("results serialize to JSON"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use serde_json::json;
+    use std::num::NonZeroUsize;
+
+    fn handle(line: &str) -> Option<String> {
+        handle_within(line, LINE_BUDGET)
+    }
+
+    fn handle_within(line: &str, budget: Duration) -> Option<String> {
+        let primes = Primes::new(1000, NonZeroUsize::new(16).unwrap());
+
+        super::handle(line.as_bytes(), &primes, budget)
+            .map(|output| serde_json::to_string(&output).unwrap())
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(
+            handle(r#"{"jsonrpc":"2.0","id":1,"method":"isPrime","params":{"number":7}}"#).unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":true}"#
+        );
+        assert_eq!(
+            handle(r#"{"jsonrpc":"2.0","id":"a","method":"factorize","params":[12]}"#).unwrap(),
+            r#"{"jsonrpc":"2.0","id":"a","result":[2,2,3]}"#
+        );
+        assert_eq!(
+            handle(r#"{"jsonrpc":"2.0","id":null,"method":"nextPrime","params":[18446744073709551615]}"#)
+                .unwrap(),
+            r#"{"jsonrpc":"2.0","id":null,"result":18446744073709551629}"#
+        );
+
+        // Notifications get nothing back, even on errors
+        assert_eq!(
+            handle(r#"{"jsonrpc":"2.0","method":"isPrime","params":[7]}"#),
+            None
+        );
+        assert_eq!(handle(r#"{"jsonrpc":"2.0","method":"nope"}"#), None);
+        assert_eq!(
+            handle(r#"[{"jsonrpc":"2.0","method":"isPrime","params":[7]}]"#),
+            None
+        );
+
+        assert_eq!(
+            handle(
+                r#"[{"jsonrpc":"2.0","id":1,"method":"primeCount","params":[10]},
+                    {"jsonrpc":"2.0","method":"isPrime","params":[7]},
+                    1,
+                    {"jsonrpc":"2.0","id":2,"method":"isPrimeBatch","params":{"numbers":[2,4]}}]"#
+            )
+            .unwrap(),
+            r#"[{"jsonrpc":"2.0","id":1,"result":4},{"jsonrpc":"2.0","id":null,"error":{"code":-32600,"message":"Invalid Request","data":"invalid type: integer `1`, expected struct RpcRequest at line 1 column 1"}},{"jsonrpc":"2.0","id":2,"result":[true,false]}]"#
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error_code = |line: &str| {
+            let output = serde_json::from_str::<Value>(&handle(line).unwrap()).unwrap();
+            output["error"]["code"].as_i64().unwrap()
+        };
+
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method""#),
+            PARSE_ERROR
+        );
+        assert_eq!(error_code("[]"), INVALID_REQUEST);
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"1.0","id":1,"method":"isPrime"}"#),
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"isPrime","params":7}"#),
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"nope"}"#),
+            METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"isPrime"}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"isPrime","params":[1,2]}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"prevPrime","params":[2]}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            error_code(r#"{"jsonrpc":"2.0","id":1,"method":"primeCount","params":[1e12]}"#),
+            OVER_BUDGET
+        );
+    }
+
+    #[test]
+    fn test_line_budget() {
+        // (2^61 - 1)(2^89 - 1) takes factorize its whole budget to give up on
+        let semiprime = ((BigUint::from(1u32) << 61) - 1u32) * ((BigUint::from(1u32) << 89) - 1u32);
+        let calls = (1..=16)
+            .map(|id| {
+                format!(
+                    r#"{{"jsonrpc":"2.0","id":{id},"method":"factorize","params":[{semiprime}]}}"#
+                )
+            })
+            .collect::<Vec<_>>();
+        let line = format!(
+            r#"[{{"jsonrpc":"2.0","id":0,"method":"isPrime","params":[7]}},{},{{"jsonrpc":"2.0","id":17,"method":"nope"}}]"#,
+            calls.join(",")
+        );
+
+        let output =
+            serde_json::from_str::<Value>(&handle_within(&line, Duration::ZERO).unwrap()).unwrap();
+        let responses = output.as_array().unwrap();
+        assert_eq!(responses.len(), 18);
+        assert_eq!(responses[0]["result"], json!(true));
+        for (id, response) in responses.iter().enumerate().take(17).skip(1) {
+            assert_eq!(response["id"], json!(id));
+            assert_eq!(response["error"]["code"], json!(OVER_BUDGET));
+        }
+        assert_eq!(responses[17]["error"]["code"], json!(METHOD_NOT_FOUND));
+    }
+}
@@ -0,0 +1,131 @@
+/// Odd numbers sieved at a time, so that a segment of the bitmap stays in cache while every base
+/// prime crosses its multiples off.
+const SEGMENT_LEN: u64 = 1 << 18;
+
+/// A bitmap of the primes up to a limit, odd numbers only, built by a segmented sieve of
+/// Eratosthenes.
+#[derive(Debug)]
+pub struct Sieve {
+    limit: u64,
+    /// Bit `i` is whether `2 * i + 1` is prime.
+    odd_primes: Vec<u64>,
+}
+
+impl Sieve {
+    pub fn new(limit: u64) -> Self {
+        let len = limit / 2 + 1;
+        let mut odd_primes = vec![u64::MAX; len.div_ceil(64) as usize];
+        // 1 is not prime
+        odd_primes[0] &= !1;
+
+        let base_primes = odd_primes_up_to(limit.isqrt());
+
+        for segment_start in (0..len).step_by(SEGMENT_LEN as usize) {
+            let segment_end = (segment_start + SEGMENT_LEN).min(len);
+            let low = 2 * segment_start + 1;
+
+            for &p in &base_primes {
+                // The first odd multiple in the segment, from p^2 since smaller ones have a
+                // smaller factor
+                let mut multiple = (p * p).max(low.div_ceil(p) * p);
+                if multiple.is_multiple_of(2) {
+                    multiple += p;
+                }
+
+                // Odd multiples are 2p apart, p apart in the bitmap
+                for i in ((multiple / 2)..segment_end).step_by(p as usize) {
+                    odd_primes[(i / 64) as usize] &= !(1 << (i % 64));
+                }
+            }
+        }
+
+        Self { limit, odd_primes }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// `None` above the limit.
+    pub fn is_prime(&self, n: u64) -> Option<bool> {
+        if n > self.limit {
+            None
+        } else if n.is_multiple_of(2) {
+            Some(n == 2)
+        } else {
+            let i = n / 2;
+            Some(self.odd_primes[(i / 64) as usize] & (1 << (i % 64)) != 0)
+        }
+    }
+
+    /// The number of primes up to and including `n`, `None` above the limit.
+    pub fn prime_count(&self, n: u64) -> Option<u64> {
+        if n > self.limit {
+            return None;
+        }
+        if n < 2 {
+            return Some(0);
+        }
+
+        // Odd primes up to n, plus 2
+        let len = (n - 1) / 2 + 1;
+        let full_words = (len / 64) as usize;
+        let count = self.odd_primes[..full_words]
+            .iter()
+            .map(|word| u64::from(word.count_ones()))
+            .sum::<u64>();
+        let partial = match len % 64 {
+            0 => 0,
+            bits => (self.odd_primes[full_words] & ((1 << bits) - 1)).count_ones(),
+        };
+
+        Some(count + u64::from(partial) + 1)
+    }
+}
+
+/// Odd primes up to `n` by a plain sieve, for the base primes up to a segmented sieve's square root.
+fn odd_primes_up_to(n: u64) -> Vec<u64> {
+    let n = n as usize;
+    let mut is_composite = vec![false; n + 1];
+    let mut primes = Vec::new();
+
+    for i in 2..=n {
+        if is_composite[i] {
+            continue;
+        }
+
+        if i > 2 {
+            primes.push(i as u64);
+        }
+        for multiple in (i * i..=n).step_by(i) {
+            is_composite[multiple] = true;
+        }
+    }
+
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primality::is_prime_u64;
+
+    #[test]
+    fn test_sieve() {
+        // Over a few segments, ending mid-word
+        let sieve = Sieve::new(1_000_003);
+
+        for n in (0..50_000).chain(999_000..=1_000_003) {
+            assert_eq!(sieve.is_prime(n), Some(is_prime_u64(n)), "{n}");
+        }
+        assert_eq!(sieve.is_prime(1_000_004), None);
+
+        assert_eq!(sieve.prime_count(0), Some(0));
+        assert_eq!(sieve.prime_count(2), Some(1));
+        assert_eq!(sieve.prime_count(10), Some(4));
+        assert_eq!(sieve.prime_count(127), Some(31));
+        assert_eq!(sieve.prime_count(128), Some(31));
+        assert_eq!(sieve.prime_count(1_000_000), Some(78_498));
+        assert_eq!(sieve.prime_count(1_000_003), Some(78_499));
+    }
+}
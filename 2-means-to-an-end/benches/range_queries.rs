@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use protohackers_2_means_to_an_end::{index::PriceIndex, Price, Timestamp};
+use std::collections::BTreeMap;
+
+/// Shuffled timestamps, a tenth of them inserted twice.
+fn points(len: i64) -> impl Iterator<Item = (Timestamp, Price)> {
+    (0..len + len / 10).map(move |i| (i * 7919 % len, i % 1000 + 100))
+}
+
+/// A mean over the middle half, as clients that insert a lot then query wide ranges do.
+fn wide_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide_query");
+    for len in [1_000, 100_000, 1_000_000] {
+        let map = points(len).collect::<BTreeMap<_, _>>();
+        let mut index = PriceIndex::new();
+        points(len).for_each(|(timestamp, price)| index.insert(timestamp, price));
+        let (mintime, maxtime) = (len / 4, len * 3 / 4);
+
+        group.bench_with_input(BenchmarkId::new("btree_map", len), &map, |b, map| {
+            b.iter(|| {
+                let (sum, count) = map
+                    .range(black_box(mintime)..=black_box(maxtime))
+                    .fold((0i128, 0i128), |(sum, count), (_, &price)| {
+                        (sum + i128::from(price), count + 1)
+                    });
+                sum / count
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("price_index", len), &index, |b, index| {
+            b.iter(|| index.summary(black_box(mintime), black_box(maxtime)).mean())
+        });
+    }
+    group.finish();
+}
+
+/// What the summaries cost on the way in, one insert at a time like a session.
+fn insert(c: &mut Criterion) {
+    let len = 100_000;
+    let mut group = c.benchmark_group("insert");
+    group.bench_function(BenchmarkId::new("btree_map", len), |b| {
+        b.iter(|| {
+            let mut map = BTreeMap::new();
+            points(len).for_each(|(timestamp, price)| {
+                map.insert(timestamp, price);
+            });
+            map
+        })
+    });
+    group.bench_function(BenchmarkId::new("price_index", len), |b| {
+        b.iter(|| {
+            let mut index = PriceIndex::new();
+            points(len).for_each(|(timestamp, price)| index.insert(timestamp, price));
+            index
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, wide_query, insert);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+use crate::{Price, Timestamp};
+
+/// Most buckets an OHLC query may return.
+pub const MAX_BUCKETS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bucket {
+    pub start: Timestamp,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+}
+
+/// Truncated towards zero, 0 when there are no prices.
+pub fn mean(prices: impl Iterator<Item = Price>) -> Price {
+    // Can't overflow, even 2^64 prices of 2^63 only add up to 2^127
+    let (sum, count) = prices.fold((0i128, 0i128), |(sum, count), price| {
+        (sum + i128::from(price), count + 1)
+    });
+
+    let mean = if count > 0 { sum / count } else { 0 };
+
+    Price::try_from(mean).expect("a mean is between its prices")
+}
+
+/// The mean of the middle two prices for even counts.
+pub fn median(mut prices: Vec<Price>) -> Option<Price> {
+    if prices.is_empty() {
+        return None;
+    }
+
+    let len = prices.len();
+    let (lower_half, &mut upper, _) = prices.select_nth_unstable(len / 2);
+    if len % 2 == 1 {
+        return Some(upper);
+    }
+
+    let lower = *lower_half
+        .iter()
+        .max()
+        .expect("even counts have a lower half");
+
+    Some(mean([lower, upper].into_iter()))
+}
+
+/// Nearest-rank: the smallest price with at least `percentile`% of the prices at or below it.
+pub fn percentile(mut prices: Vec<Price>, percentile: u8) -> Option<Price> {
+    if prices.is_empty() {
+        return None;
+    }
+
+    let rank = (prices.len() * usize::from(percentile))
+        .div_ceil(100)
+        .max(1);
+
+    Some(*prices.select_nth_unstable(rank - 1).1)
+}
+
+/// Buckets of `width` from `start`, skipping empty ones, over `points` sorted by timestamp from
+/// `start` on. `None` past [`MAX_BUCKETS`].
+pub fn ohlc(
+    points: impl Iterator<Item = (Timestamp, Price)>,
+    start: Timestamp,
+    width: i64,
+) -> Option<Vec<Bucket>> {
+    let mut buckets = Vec::<Bucket>::new();
+
+    for (timestamp, price) in points {
+        // Timestamps can span the whole i64 range
+        let index = (i128::from(timestamp) - i128::from(start)) / i128::from(width);
+        let bucket_start = (i128::from(start) + index * i128::from(width)) as Timestamp;
+
+        let full = buckets.len() == MAX_BUCKETS;
+
+        match buckets.last_mut() {
+            Some(bucket) if bucket.start == bucket_start => {
+                bucket.high = bucket.high.max(price);
+                bucket.low = bucket.low.min(price);
+                bucket.close = price;
+            }
+            _ if full => return None,
+            _ => buckets.push(Bucket {
+                start: bucket_start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+            }),
+        }
+    }
+
+    Some(buckets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregates() {
+        assert_eq!(mean([i64::MAX, i64::MAX - 2].into_iter()), i64::MAX - 1);
+
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![5, 1, 3]), Some(3));
+        assert_eq!(median(vec![5, 1, 4, 2]), Some(3));
+
+        let prices = (1..=20).rev().collect::<Vec<_>>();
+        assert_eq!(percentile(prices.clone(), 0), Some(1));
+        assert_eq!(percentile(prices.clone(), 5), Some(1));
+        assert_eq!(percentile(prices.clone(), 6), Some(2));
+        assert_eq!(percentile(prices.clone(), 50), Some(10));
+        assert_eq!(percentile(prices, 100), Some(20));
+
+        let points = [(10, 5), (12, 7), (14, 3), (19, 4), (35, 9)];
+        assert_eq!(
+            ohlc(points.into_iter(), 10, 10),
+            Some(vec![
+                Bucket {
+                    start: 10,
+                    open: 5,
+                    high: 7,
+                    low: 3,
+                    close: 4
+                },
+                Bucket {
+                    start: 30,
+                    open: 9,
+                    high: 9,
+                    low: 9,
+                    close: 9
+                },
+            ])
+        );
+        assert_eq!(ohlc((0..=MAX_BUCKETS as i64).map(|t| (t, t)), 0, 1), None);
+    }
+}
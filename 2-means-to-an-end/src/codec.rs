@@ -0,0 +1,171 @@
+use crate::{
+    request::{Request, RequestError},
+    response::Response,
+    Version,
+};
+use bytes::BytesMut;
+use protohackers_utils::FixedSizeCodec;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Messages of the connection's current protocol version, sized by their type, or by their length
+/// byte for `N`.
+///
+/// Decoding a [`Request::Version`] switches versions right away, so that the rest of the buffer
+/// and the response to it already use the new one. Requests that can't be parsed are still
+/// decoded, as errors to answer.
+#[derive(Debug, Default)]
+pub struct MessageCodec {
+    version: Version,
+    v1_request: FixedSizeCodec<{ 1 + 2 * 4 }>,
+    v1_long_request: FixedSizeCodec<{ 1 + 3 * 4 }>,
+    v2_request: FixedSizeCodec<{ 1 + 2 * 8 }>,
+    v2_long_request: FixedSizeCodec<{ 1 + 3 * 8 }>,
+}
+
+impl MessageCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Result<Request, RequestError>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let Some(&typ) = src.first() else {
+            return Ok(None);
+        };
+
+        if typ == b'N' {
+            let Some(&len) = src.get(1) else {
+                return Ok(None);
+            };
+            let len = 2 + usize::from(len);
+            if src.len() < len {
+                src.reserve(len - src.len());
+                return Ok(None);
+            }
+
+            return Ok(Some(Request::parse_series(&src.split_to(len))));
+        }
+
+        let version = self.version;
+        let parse = |frame: &[u8]| Request::parse(frame, version);
+        let request = match (version, Request::field_count(typ)) {
+            (Version::V1, 2) => self.v1_request.decode(src)?.map(|frame| parse(&frame)),
+            (Version::V1, _) => self.v1_long_request.decode(src)?.map(|frame| parse(&frame)),
+            (Version::V2, 2) => self.v2_request.decode(src)?.map(|frame| parse(&frame)),
+            (Version::V2, _) => self.v2_long_request.decode(src)?.map(|frame| parse(&frame)),
+        };
+
+        if let Some(Ok(Request::Version(version))) = request {
+            self.version = version;
+        }
+
+        Ok(request)
+    }
+}
+
+impl Encoder<Response> for MessageCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Response, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.encode(self.version, dst);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::Aggregate;
+
+    #[test]
+    fn test_version_switch() {
+        let mut codec = MessageCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(b"V\x00\x00\x00\x02\x00\x00\x00\x00");
+        src.extend_from_slice(b"I\x00\x00\x00\x01\x00\x00\x00\x00\x7f\xff\xff\xff\xff\xff\xff\xff");
+
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Ok(Request::Version(Version::V2)))
+        ));
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Ok(Request::Insert {
+                timestamp: 0x1_0000_0000,
+                price: i64::MAX
+            }))
+        ));
+
+        let mut dst = BytesMut::new();
+        codec.encode(Response::new(-2), &mut dst).unwrap();
+        assert_eq!(&dst[..], (-2i64).to_be_bytes());
+
+        // Unsupported versions are answered, and don't switch
+        src.extend_from_slice(b"V\x00\x00\x00\x00\x00\x00\x00\x03\x00\x00\x00\x00\x00\x00\x00\x00");
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Err(RequestError::UnsupportedVersion(3)))
+        ));
+        assert_eq!(codec.version, Version::V2);
+    }
+
+    #[test]
+    fn test_aggregate_framing() {
+        let mut codec = MessageCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(b"P\x00\x00\x00\x01\x00\x00\x00\x02\x00\x00\x00");
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(b"\x5a?\x00\x00\x00\x00\x00\x00\x00\x00");
+
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Ok(Request::Aggregate {
+                mintime: 1,
+                maxtime: 2,
+                aggregate: Aggregate::Percentile(90)
+            }))
+        ));
+        // Unknown types take as much as most messages
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Err(RequestError::BadType(b'?')))
+        ));
+        assert!(src.is_empty());
+
+        let mut dst = BytesMut::new();
+        codec.encode(Response::Sum(1 << 40), &mut dst).unwrap();
+        codec.encode(Response::error("nope"), &mut dst).unwrap();
+        assert_eq!(&dst[..], b"R\x00\x00\x01\x00\x00\x00\x00\x00E\x04nope");
+    }
+
+    #[test]
+    fn test_series_framing() {
+        let mut codec = MessageCodec::new();
+        let mut src = BytesMut::new();
+        src.extend_from_slice(b"N");
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(b"\x03BT");
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        src.extend_from_slice(b"CN\x00N\x01\xff");
+
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Ok(Request::Series(name))) if name == "BTC"
+        ));
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Ok(Request::Series(name))) if name.is_empty()
+        ));
+        assert!(matches!(
+            codec.decode(&mut src).unwrap(),
+            Some(Err(RequestError::InvalidSeriesName))
+        ));
+        assert!(src.is_empty());
+    }
+}
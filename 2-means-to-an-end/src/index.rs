@@ -0,0 +1,388 @@
+use crate::{Price, Timestamp};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+const NIL: u32 = u32::MAX;
+
+/// Aggregates of a set of prices, combinable in any order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub count: u64,
+    /// Can't overflow, even 2^64 prices of 2^63 only add up to 2^127.
+    pub sum: i128,
+    min: Price,
+    max: Price,
+}
+
+impl Summary {
+    pub const EMPTY: Self = Self {
+        count: 0,
+        sum: 0,
+        min: Price::MAX,
+        max: Price::MIN,
+    };
+
+    fn single(price: Price) -> Self {
+        Self {
+            count: 1,
+            sum: price.into(),
+            min: price,
+            max: price,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            count: self.count + other.count,
+            sum: self.sum + other.sum,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn min(&self) -> Option<Price> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<Price> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Truncated towards zero, 0 when empty.
+    pub fn mean(&self) -> Price {
+        let mean = if self.count > 0 {
+            self.sum / i128::from(self.count)
+        } else {
+            0
+        };
+
+        Price::try_from(mean).expect("a mean is between its prices")
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    timestamp: Timestamp,
+    price: Price,
+    priority: u64,
+    left: u32,
+    right: u32,
+    /// Of the whole subtree.
+    summary: Summary,
+}
+
+/// Prices by timestamp, answering range [`Summary`]s in O(log n).
+///
+/// A treap whose nodes also summarize their subtree, stored in an arena. Like a `BTreeMap`,
+/// inserting at an existing timestamp replaces its price.
+#[derive(Debug)]
+pub struct PriceIndex {
+    nodes: Vec<Node>,
+    root: u32,
+    rng_state: u64,
+}
+
+impl PriceIndex {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            root: NIL,
+            // Random so that clients can't force a degenerate tree, and never 0 for xorshift
+            rng_state: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn insert(&mut self, timestamp: Timestamp, price: Price) {
+        if self.replace(self.root, timestamp, price) {
+            return;
+        }
+
+        let node = u32::try_from(self.nodes.len()).expect("fewer than 2^32 prices");
+        let priority = self.next_priority();
+        self.nodes.push(Node {
+            timestamp,
+            price,
+            priority,
+            left: NIL,
+            right: NIL,
+            summary: Summary::single(price),
+        });
+
+        self.root = self.insert_node(self.root, node);
+    }
+
+    /// Aggregates of the prices from `mintime` to `maxtime` included.
+    pub fn summary(&self, mintime: Timestamp, maxtime: Timestamp) -> Summary {
+        // The topmost node in range, under which its bounds split
+        let mut top = self.root;
+        while top != NIL {
+            let node = &self.nodes[top as usize];
+            if node.timestamp < mintime {
+                top = node.right;
+            } else if node.timestamp > maxtime {
+                top = node.left;
+            } else {
+                break;
+            }
+        }
+        if top == NIL {
+            return Summary::EMPTY;
+        }
+
+        let mut summary = Summary::single(self.nodes[top as usize].price);
+
+        // On the left, whole right subtrees are in range whenever their parent is
+        let mut node = self.nodes[top as usize].left;
+        while node != NIL {
+            let Node {
+                timestamp,
+                price,
+                left,
+                right,
+                ..
+            } = self.nodes[node as usize];
+            if timestamp >= mintime {
+                summary = summary
+                    .merge(Summary::single(price))
+                    .merge(self.subtree_summary(right));
+                node = left;
+            } else {
+                node = right;
+            }
+        }
+
+        let mut node = self.nodes[top as usize].right;
+        while node != NIL {
+            let Node {
+                timestamp,
+                price,
+                left,
+                right,
+                ..
+            } = self.nodes[node as usize];
+            if timestamp <= maxtime {
+                summary = summary
+                    .merge(Summary::single(price))
+                    .merge(self.subtree_summary(left));
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+
+        summary
+    }
+
+    /// The prices from `mintime` to `maxtime` included, by timestamp.
+    pub fn range(
+        &self,
+        mintime: Timestamp,
+        maxtime: Timestamp,
+    ) -> impl Iterator<Item = (Timestamp, Price)> + '_ {
+        // The path to the first node in range, to be visited in order
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        while node != NIL {
+            let current = &self.nodes[node as usize];
+            if current.timestamp >= mintime {
+                stack.push(node);
+                node = current.left;
+            } else {
+                node = current.right;
+            }
+        }
+
+        std::iter::from_fn(move || {
+            let node = &self.nodes[stack.pop()? as usize];
+            if node.timestamp > maxtime {
+                stack.clear();
+                return None;
+            }
+
+            let mut next = node.right;
+            while next != NIL {
+                stack.push(next);
+                next = self.nodes[next as usize].left;
+            }
+
+            Some((node.timestamp, node.price))
+        })
+    }
+
+    fn subtree_summary(&self, node: u32) -> Summary {
+        match node {
+            NIL => Summary::EMPTY,
+            node => self.nodes[node as usize].summary,
+        }
+    }
+
+    fn update_summary(&mut self, node: u32) {
+        let Node {
+            price, left, right, ..
+        } = self.nodes[node as usize];
+
+        self.nodes[node as usize].summary = self
+            .subtree_summary(left)
+            .merge(Summary::single(price))
+            .merge(self.subtree_summary(right));
+    }
+
+    /// Replaces the price at `timestamp` if it's under `node`, updating summaries on the way up.
+    fn replace(&mut self, node: u32, timestamp: Timestamp, price: Price) -> bool {
+        if node == NIL {
+            return false;
+        }
+
+        let Node {
+            timestamp: current,
+            left,
+            right,
+            ..
+        } = self.nodes[node as usize];
+        let replaced = match timestamp.cmp(&current) {
+            std::cmp::Ordering::Equal => {
+                self.nodes[node as usize].price = price;
+                true
+            }
+            std::cmp::Ordering::Less => self.replace(left, timestamp, price),
+            std::cmp::Ordering::Greater => self.replace(right, timestamp, price),
+        };
+
+        if replaced {
+            self.update_summary(node);
+        }
+
+        replaced
+    }
+
+    /// Splits the subtree under `node` into timestamps before and after `timestamp`, which is not
+    /// in it.
+    fn split(&mut self, node: u32, timestamp: Timestamp) -> (u32, u32) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+
+        let current = &self.nodes[node as usize];
+        if current.timestamp < timestamp {
+            let (left, right) = self.split(current.right, timestamp);
+            self.nodes[node as usize].right = left;
+            self.update_summary(node);
+
+            (node, right)
+        } else {
+            let (left, right) = self.split(current.left, timestamp);
+            self.nodes[node as usize].left = right;
+            self.update_summary(node);
+
+            (left, node)
+        }
+    }
+
+    /// Inserts `new` under `node`, where its priority puts it, and returns the subtree's root.
+    fn insert_node(&mut self, node: u32, new: u32) -> u32 {
+        if node == NIL {
+            return new;
+        }
+
+        let Node {
+            timestamp,
+            priority,
+            ..
+        } = self.nodes[new as usize];
+        if priority > self.nodes[node as usize].priority {
+            let (left, right) = self.split(node, timestamp);
+            self.nodes[new as usize].left = left;
+            self.nodes[new as usize].right = right;
+            self.update_summary(new);
+
+            return new;
+        }
+
+        let current = &self.nodes[node as usize];
+        if timestamp < current.timestamp {
+            let left = self.insert_node(current.left, new);
+            self.nodes[node as usize].left = left;
+        } else {
+            let right = self.insert_node(current.right, new);
+            self.nodes[node as usize].right = right;
+        }
+        self.update_summary(node);
+
+        node
+    }
+
+    /// xorshift64
+    fn next_priority(&mut self) -> u64 {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+
+        self.rng_state
+    }
+}
+
+impl Default for PriceIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_matches_btree_map() {
+        let mut index = PriceIndex::new();
+        let mut map = BTreeMap::new();
+        let mut seed = 42u64;
+        let mut random = |range: i64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as i64 % range - range / 2
+        };
+
+        for i in 0..3000 {
+            // Few enough timestamps for plenty of duplicates
+            let (timestamp, price) = (random(2000), random(1 << 40));
+            index.insert(timestamp, price);
+            map.insert(timestamp, price);
+
+            if i % 10 == 0 {
+                let (a, b) = (random(2200), random(2200));
+                let (mintime, maxtime) = (a.min(b), a.max(b));
+                let expected = map.range(mintime..=maxtime).map(|(&t, &p)| (t, p));
+
+                assert_eq!(
+                    index.range(mintime, maxtime).collect::<Vec<_>>(),
+                    expected.clone().collect::<Vec<_>>()
+                );
+
+                let summary = index.summary(mintime, maxtime);
+                assert_eq!(summary.count, expected.clone().count() as u64);
+                assert_eq!(
+                    summary.sum,
+                    expected.clone().map(|(_, p)| i128::from(p)).sum()
+                );
+                assert_eq!(summary.min(), expected.clone().map(|(_, p)| p).min());
+                assert_eq!(summary.max(), expected.map(|(_, p)| p).max());
+            }
+        }
+
+        assert_eq!(index.len(), map.len());
+        assert_eq!(index.summary(1, 0), Summary::EMPTY);
+        assert_eq!(index.range(i64::MIN, i64::MAX).count(), map.len());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod index;
+
+pub type Timestamp = i64;
+
+pub type Price = i64;
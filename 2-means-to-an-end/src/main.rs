@@ -1,80 +1,204 @@
+mod aggregate;
+mod codec;
 mod request;
 mod response;
-
-use futures::{SinkExt, StreamExt};
-use protohackers_utils::{default_tcp_listen, FixedSizeCodec, TryFromDecoder, TryIntoEncoder};
-use request::Request;
-use std::{collections::BTreeMap, net::SocketAddr};
-use tokio::net::TcpStream;
-use tokio_util::codec::{FramedRead, FramedWrite};
+mod series;
+
+use codec::MessageCodec;
+use futures::future;
+use protohackers_2_means_to_an_end::{index::PriceIndex, Price, Timestamp};
+use protohackers_utils::{
+    default_tcp_listen, serve_framed, ConnectionContext, ConnectionStream, InspectCodec,
+};
+use request::{Aggregate, Request, RequestError};
+use series::{Limits, Registry, Session};
+use std::{env, sync::Arc, time::Duration};
+use tokio::time;
+use tokio_util::codec::Framed;
+use tower::service_fn;
 
 use crate::response::Response;
 
-type Timestamp = i32;
+/// Seconds a named series nobody has selected outlives its last use.
+const SERIES_RETENTION_ENV_VAR: &str = "MEANS_TO_AN_END_SERIES_RETENTION";
+const DEFAULT_SERIES_RETENTION: Duration = Duration::from_secs(60 * 60);
 
-type Price = i32;
+/// How often expired series are dropped, besides whenever one is selected.
+const SERIES_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
-// TODO: Arbitrary length integers
+const SERIES_MAX_PRICES_ENV_VAR: &str = "MEANS_TO_AN_END_SERIES_MAX_PRICES";
+const DEFAULT_SERIES_MAX_PRICES: usize = 100_000;
 
-async fn handle_client(mut stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
-    let (read, write) = stream.split();
+/// Most named series at a time, selecting a new one past this is answered with an error.
+const MAX_SERIES_ENV_VAR: &str = "MEANS_TO_AN_END_MAX_SERIES";
+const DEFAULT_MAX_SERIES: usize = 256;
 
-    let mut read_framed = FramedRead::new(read, FixedSizeCodec::<{ Request::SIZE }>::new())
-        .map_decoder(TryFromDecoder::new);
-    let mut write_framed =
-        FramedWrite::new(write, FixedSizeCodec::<{ i32::BITS as usize / 8 }>::new())
-            .map_encoder(TryIntoEncoder::new);
+/// Most prices in all named series together, about 80 bytes each.
+const MAX_SHARED_PRICES_ENV_VAR: &str = "MEANS_TO_AN_END_MAX_SHARED_PRICES";
+const DEFAULT_MAX_SHARED_PRICES: usize = 1_000_000;
 
-    let mut data: BTreeMap<Timestamp, Price> = BTreeMap::new();
+/// Protocol versions, switched between by a `V` message. Version 1 has 32-bit fields, version 2
+/// 64-bit ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Version {
+    #[default]
+    V1,
+    V2,
+}
 
-    while let Some(item) = read_framed.next().await {
-        #[cfg(debug_assertions)]
-        println!("{addr} --> {item:?}");
+impl TryFrom<i64> for Version {
+    type Error = i64;
 
-        let response = match item {
-            Ok(Request::Insert { timestamp, price }) => {
-                data.insert(timestamp, price);
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(value),
+        }
+    }
+}
 
-                None
-            }
-            Ok(Request::Query { mintime, maxtime }) => {
-                if mintime <= maxtime {
-                    let (sum, count) = data.range(mintime..=maxtime).try_fold(
-                        (0i128, 0i128),
-                        |(sum, count), (_, &price)| {
-                            Ok::<_, anyhow::Error>((
-                                sum.checked_add(price as i128)
-                                    .ok_or(anyhow::Error::msg("could not add properly"))?,
-                                count + 1,
-                            ))
-                        },
-                    )?;
-
-                    let mean = if count > 0 { sum / count } else { 0 };
-
-                    Some(Response::new(i32::try_from(mean)?))
-                } else {
-                    Some(Response::new(0))
-                }
+impl Version {
+    pub fn field_width(self) -> usize {
+        match self {
+            Self::V1 => 4,
+            Self::V2 => 8,
+        }
+    }
+}
+
+impl From<Version> for i64 {
+    fn from(version: Version) -> Self {
+        match version {
+            Version::V1 => 1,
+            Version::V2 => 2,
+        }
+    }
+}
+
+fn handle_request(
+    session: &mut Session,
+    request: Result<Request, RequestError>,
+) -> anyhow::Result<Option<Response>> {
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => return Ok(Some(Response::error(err))),
+    };
+
+    match request {
+        Request::Insert { timestamp, price } => {
+            // Inserts are never answered, an error would be mistaken for the next query's answer
+            if let Err(err) = session.insert(timestamp, price) {
+                println!("Dropping insert: {err}");
             }
-            Err(err) => return Err(err.into()),
-        };
 
-        // TODO: Use response
-        if let Some(response) = response {
-            #[cfg(debug_assertions)]
-            println!("{addr} <-- {response:?}");
+            Ok(None)
+        }
+        Request::Query { mintime, maxtime } => Ok(Some(Response::new(
+            session.read(|data| data.summary(mintime, maxtime).mean()),
+        ))),
+        Request::Aggregate {
+            mintime,
+            maxtime,
+            aggregate,
+        } => Ok(Some(session.read(|data| {
+            handle_aggregate(data, mintime, maxtime, aggregate)
+        }))),
+        // Confirmed with how many prices the series already has, tagged to tell it from an error
+        Request::Series(name) => match session.select(name) {
+            Ok(len) => Ok(Some(Response::Value(len as Price))),
+            Err(err) => Ok(Some(Response::error(err))),
+        },
+        // The codec already switched, confirm in the new version
+        Request::Version(version) => Ok(Some(Response::new(version.into()))),
+    }
+}
 
-            write_framed.send(response).await?;
+fn handle_aggregate(
+    data: &PriceIndex,
+    mintime: Timestamp,
+    maxtime: Timestamp,
+    aggregate: Aggregate,
+) -> Response {
+    let points = || data.range(mintime, maxtime);
+    let prices = || points().map(|(_, price)| price);
+    let summary = || data.summary(mintime, maxtime);
+    let value_or_empty = |value: Option<Price>| match value {
+        Some(value) => Response::Value(value),
+        None => Response::error("no prices in range"),
+    };
+
+    match aggregate {
+        Aggregate::Min => value_or_empty(summary().min()),
+        Aggregate::Max => value_or_empty(summary().max()),
+        Aggregate::Count => Response::Value(summary().count as Price),
+        Aggregate::Sum => Response::Sum(summary().sum),
+        Aggregate::Median => value_or_empty(aggregate::median(prices().collect())),
+        Aggregate::Percentile(percentile) => {
+            value_or_empty(aggregate::percentile(prices().collect(), percentile))
         }
+        Aggregate::Ohlc { width } => match aggregate::ohlc(points(), mintime, width) {
+            Some(buckets) => Response::Buckets(buckets),
+            None => Response::error(format!("more than {} buckets", aggregate::MAX_BUCKETS)),
+        },
     }
+}
+
+async fn handle_client(
+    stream: ConnectionStream,
+    ctx: ConnectionContext,
+    registry: Arc<Registry>,
+) -> anyhow::Result<()> {
+    let framed = Framed::new(stream, InspectCodec::new(MessageCodec::new(), ctx));
+
+    let mut session = Session::new(registry);
+
+    let service = service_fn(|request| future::ready(handle_request(&mut session, request)));
+
+    serve_framed(framed, service).await?;
 
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    default_tcp_listen(handle_client).await?;
+    let retention = match env::var(SERIES_RETENTION_ENV_VAR) {
+        Ok(retention) => Duration::from_secs(retention.parse()?),
+        Err(_) => DEFAULT_SERIES_RETENTION,
+    };
+    let max_prices = match env::var(SERIES_MAX_PRICES_ENV_VAR) {
+        Ok(max_prices) => max_prices.parse()?,
+        Err(_) => DEFAULT_SERIES_MAX_PRICES,
+    };
+    let max_series = match env::var(MAX_SERIES_ENV_VAR) {
+        Ok(max_series) => max_series.parse()?,
+        Err(_) => DEFAULT_MAX_SERIES,
+    };
+    let max_total_prices = match env::var(MAX_SHARED_PRICES_ENV_VAR) {
+        Ok(max_total_prices) => max_total_prices.parse()?,
+        Err(_) => DEFAULT_MAX_SHARED_PRICES,
+    };
+    let registry = Arc::new(Registry::new(Limits {
+        retention,
+        max_prices,
+        max_series,
+        max_total_prices,
+    }));
+
+    tokio::spawn({
+        let registry = Arc::clone(&registry);
+        let mut interval = time::interval(SERIES_SWEEP_INTERVAL);
+
+        async move {
+            loop {
+                interval.tick().await;
+                registry.sweep();
+            }
+        }
+    });
+
+    default_tcp_listen(move |stream, ctx| handle_client(stream, ctx, Arc::clone(&registry)))
+        .await?;
 
     Ok(())
 }
@@ -1,36 +1,102 @@
 use thiserror::Error;
 
-use crate::{Price, Timestamp};
+use crate::{Price, Timestamp, Version};
 
 #[derive(Debug, Error)]
 pub enum RequestError {
     #[error("received bad type tag: {0}")]
     BadType(u8),
+    #[error("unsupported protocol version: {0}")]
+    UnsupportedVersion(i64),
+    #[error("percentile out of 0..=100: {0}")]
+    InvalidPercentile(i64),
+    #[error("bucket width must be positive: {0}")]
+    InvalidBucketWidth(i64),
+    #[error("series name isn't UTF-8")]
+    InvalidSeriesName,
 }
 
+/// A type byte followed by big-endian fields, 32-bit in version 1 and 64-bit in version 2.
+///
+/// Most messages have two fields, percentile and OHLC queries a third. Unknown types are assumed
+/// to have two. `N` has a name prefixed by its u8 length instead.
 #[derive(Debug)]
 pub enum Request {
     Insert {
         timestamp: Timestamp,
         price: Price,
     },
+    /// The mean price between two timestamps.
     Query {
         mintime: Timestamp,
         maxtime: Timestamp,
     },
+    /// Switches the connection to another protocol version, from the next message on.
+    Version(Version),
+    Aggregate {
+        mintime: Timestamp,
+        maxtime: Timestamp,
+        aggregate: Aggregate,
+    },
+    /// Selects a named series shared with other connections, or back the connection's own one
+    /// when empty.
+    Series(String),
 }
 
-impl Request {
-    pub const SIZE: usize = 9;
+/// Aggregates over the prices between two timestamps, besides the mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    /// `L`
+    Min,
+    /// `H`
+    Max,
+    /// `C`
+    Count,
+    /// `S`
+    Sum,
+    /// `M`
+    Median,
+    /// `P`, nearest-rank percentile from 0 to 100.
+    Percentile(u8),
+    /// `O`, open/high/low/close of each non-empty bucket of `width` from the first timestamp.
+    Ohlc { width: i64 },
 }
 
-impl TryFrom<[u8; Request::SIZE]> for Request {
-    type Error = RequestError;
+impl Request {
+    pub fn field_count(typ: u8) -> usize {
+        match typ {
+            b'P' | b'O' => 3,
+            _ => 2,
+        }
+    }
+
+    /// Parses a whole `N` frame, as sized by its length byte.
+    pub fn parse_series(frame: &[u8]) -> Result<Self, RequestError> {
+        let name =
+            String::from_utf8(frame[2..].to_vec()).map_err(|_| RequestError::InvalidSeriesName)?;
+
+        Ok(Request::Series(name))
+    }
 
-    fn try_from(value: [u8; Request::SIZE]) -> Result<Self, Self::Error> {
-        let typ = value[0];
-        let field_1 = i32::from_be_bytes([value[1], value[2], value[3], value[4]]);
-        let field_2 = i32::from_be_bytes([value[5], value[6], value[7], value[8]]);
+    /// Parses a whole frame, as sized by [`Self::field_count`].
+    pub fn parse(frame: &[u8], version: Version) -> Result<Self, RequestError> {
+        let typ = frame[0];
+        let fields = frame[1..]
+            .chunks_exact(version.field_width())
+            .map(|field| match version {
+                Version::V1 => i32::from_be_bytes(field.try_into().unwrap()).into(),
+                Version::V2 => i64::from_be_bytes(field.try_into().unwrap()),
+            })
+            .collect::<Vec<i64>>();
+        let (field_1, field_2) = (fields[0], fields[1]);
+
+        let aggregate = |aggregate| {
+            Ok(Request::Aggregate {
+                mintime: field_1,
+                maxtime: field_2,
+                aggregate,
+            })
+        };
 
         match typ {
             b'I' => Ok(Request::Insert {
@@ -41,6 +107,22 @@ impl TryFrom<[u8; Request::SIZE]> for Request {
                 mintime: field_1,
                 maxtime: field_2,
             }),
+            b'V' => Ok(Request::Version(
+                Version::try_from(field_1).map_err(RequestError::UnsupportedVersion)?,
+            )),
+            b'L' => aggregate(Aggregate::Min),
+            b'H' => aggregate(Aggregate::Max),
+            b'C' => aggregate(Aggregate::Count),
+            b'S' => aggregate(Aggregate::Sum),
+            b'M' => aggregate(Aggregate::Median),
+            b'P' => aggregate(Aggregate::Percentile(
+                u8::try_from(fields[2])
+                    .ok()
+                    .filter(|&percentile| percentile <= 100)
+                    .ok_or(RequestError::InvalidPercentile(fields[2]))?,
+            )),
+            b'O' if fields[2] > 0 => aggregate(Aggregate::Ohlc { width: fields[2] }),
+            b'O' => Err(RequestError::InvalidBucketWidth(fields[2])),
             _ => Err(RequestError::BadType(typ)),
         }
     }
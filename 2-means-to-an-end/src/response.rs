@@ -1,16 +1,75 @@
-use crate::Price;
+use crate::{aggregate::Bucket, Price, Version};
+use bytes::{BufMut, BytesMut};
 
+/// Maximum length of an error message, which is prefixed by its length as a byte.
+const MAX_ERROR_LEN: usize = u8::MAX as usize;
+
+/// Fields are as wide as in requests, saturating in version 1. Responses to the original `Q` and
+/// `V` messages are a single bare field, all others start with `R` or, on errors, `E`.
 #[derive(Debug)]
-pub struct Response(Price);
+pub enum Response {
+    Bare(Price),
+    Value(Price),
+    /// Two fields wide, since sums can exceed a single one.
+    Sum(i128),
+    /// A field with the number of buckets, then each bucket's start, open, high, low and close.
+    Buckets(Vec<Bucket>),
+    /// A byte with the message length, then the message.
+    Error(String),
+}
 
 impl Response {
     pub fn new(price: Price) -> Self {
-        Self(price)
+        Self::Bare(price)
     }
-}
 
-impl From<Response> for [u8; 4] {
-    fn from(value: Response) -> Self {
-        value.0.to_be_bytes()
+    pub fn error(err: impl ToString) -> Self {
+        Self::Error(err.to_string())
+    }
+
+    pub fn encode(self, version: Version, dst: &mut BytesMut) {
+        let put_field = |dst: &mut BytesMut, field: i64| match version {
+            Version::V1 => {
+                dst.put_i32(field.clamp(i32::MIN.into(), i32::MAX.into()) as i32);
+            }
+            Version::V2 => dst.put_i64(field),
+        };
+
+        match self {
+            Response::Bare(price) => put_field(dst, price),
+            Response::Value(price) => {
+                dst.put_u8(b'R');
+                put_field(dst, price);
+            }
+            Response::Sum(sum) => {
+                dst.put_u8(b'R');
+                match version {
+                    Version::V1 => dst.put_i64(sum.clamp(i64::MIN.into(), i64::MAX.into()) as i64),
+                    Version::V2 => dst.put_i128(sum),
+                }
+            }
+            Response::Buckets(buckets) => {
+                dst.put_u8(b'R');
+                put_field(dst, buckets.len() as i64);
+                for bucket in buckets {
+                    for field in [
+                        bucket.start,
+                        bucket.open,
+                        bucket.high,
+                        bucket.low,
+                        bucket.close,
+                    ] {
+                        put_field(dst, field);
+                    }
+                }
+            }
+            Response::Error(message) => {
+                let message = &message.as_bytes()[..message.len().min(MAX_ERROR_LEN)];
+
+                dst.put_u8(b'E');
+                dst.put_u8(message.len() as u8);
+                dst.put_slice(message);
+            }
+        }
     }
 }
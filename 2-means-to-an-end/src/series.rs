@@ -0,0 +1,251 @@
+use crate::{Price, Timestamp};
+use protohackers_2_means_to_an_end::index::PriceIndex;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SeriesError {
+    #[error("series is full at {0} prices")]
+    Full(usize),
+    #[error("no more than {0} series")]
+    TooManySeries(usize),
+    #[error("all series are full at {0} prices")]
+    OutOfPrices(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// How long a series no connection has selected is kept after its last use.
+    pub retention: Duration,
+    /// Most prices in a series, replacing one at an existing timestamp is still allowed.
+    pub max_prices: usize,
+    pub max_series: usize,
+    /// Most prices in all series together.
+    pub max_total_prices: usize,
+}
+
+#[derive(Debug)]
+struct Series {
+    prices: PriceIndex,
+    last_used: Instant,
+}
+
+/// Named series shared by all connections.
+#[derive(Debug)]
+pub struct Registry {
+    series: Mutex<HashMap<String, Arc<Mutex<Series>>>>,
+    limits: Limits,
+    total_prices: AtomicUsize,
+}
+
+impl Registry {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            series: Mutex::new(HashMap::new()),
+            limits,
+            total_prices: AtomicUsize::new(0),
+        }
+    }
+
+    /// Drops the series no connection has selected and nobody used within their retention.
+    pub fn sweep(&self) {
+        Self::sweep_locked(
+            &mut self.series.lock().unwrap(),
+            &self.limits,
+            &self.total_prices,
+        );
+    }
+
+    fn sweep_locked(
+        series: &mut HashMap<String, Arc<Mutex<Series>>>,
+        limits: &Limits,
+        total_prices: &AtomicUsize,
+    ) {
+        let now = Instant::now();
+
+        series.retain(|_, series| {
+            if Arc::strong_count(series) > 1 {
+                return true;
+            }
+
+            let series = series.lock().unwrap();
+            if now.duration_since(series.last_used) < limits.retention {
+                return true;
+            }
+
+            total_prices.fetch_sub(series.prices.len(), Ordering::Relaxed);
+            false
+        });
+    }
+
+    fn open(&self, name: String) -> Result<Arc<Mutex<Series>>, SeriesError> {
+        let now = Instant::now();
+        let mut series = self.series.lock().unwrap();
+
+        Self::sweep_locked(&mut series, &self.limits, &self.total_prices);
+        if !series.contains_key(&name) && series.len() >= self.limits.max_series {
+            return Err(SeriesError::TooManySeries(self.limits.max_series));
+        }
+
+        let series = series.entry(name).or_insert_with(|| {
+            Arc::new(Mutex::new(Series {
+                prices: PriceIndex::new(),
+                last_used: now,
+            }))
+        });
+
+        Ok(Arc::clone(series))
+    }
+}
+
+/// A connection's prices, its own anonymous series unless it selected a named one.
+#[derive(Debug)]
+pub struct Session {
+    registry: Arc<Registry>,
+    anonymous: PriceIndex,
+    named: Option<Arc<Mutex<Series>>>,
+}
+
+impl Session {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        Self {
+            registry,
+            anonymous: PriceIndex::new(),
+            named: None,
+        }
+    }
+
+    /// Selects a named series, or back the anonymous one for an empty name. Returns how many
+    /// prices it has.
+    pub fn select(&mut self, name: String) -> Result<usize, SeriesError> {
+        self.named = if name.is_empty() {
+            None
+        } else {
+            Some(self.registry.open(name)?)
+        };
+
+        Ok(self.read(PriceIndex::len))
+    }
+
+    pub fn insert(&mut self, timestamp: Timestamp, price: Price) -> Result<(), SeriesError> {
+        let Some(series) = &self.named else {
+            self.anonymous.insert(timestamp, price);
+            return Ok(());
+        };
+
+        let mut series = series.lock().unwrap();
+        series.last_used = Instant::now();
+
+        let Limits {
+            max_prices,
+            max_total_prices,
+            ..
+        } = self.registry.limits;
+        let is_new = series.prices.range(timestamp, timestamp).next().is_none();
+        if is_new {
+            if series.prices.len() >= max_prices {
+                return Err(SeriesError::Full(max_prices));
+            }
+
+            self.registry
+                .total_prices
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |total| {
+                    (total < max_total_prices).then_some(total + 1)
+                })
+                .map_err(|_| SeriesError::OutOfPrices(max_total_prices))?;
+        }
+
+        series.prices.insert(timestamp, price);
+
+        Ok(())
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&PriceIndex) -> T) -> T {
+        let Some(series) = &self.named else {
+            return f(&self.anonymous);
+        };
+
+        let mut series = series.lock().unwrap();
+        series.last_used = Instant::now();
+
+        f(&series.prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_series() {
+        let registry = Arc::new(Registry::new(Limits {
+            retention: Duration::from_secs(60),
+            max_prices: 2,
+            max_series: 16,
+            max_total_prices: 16,
+        }));
+        let mut alice = Session::new(Arc::clone(&registry));
+        let mut bob = Session::new(Arc::clone(&registry));
+
+        alice.insert(1, 10).unwrap();
+        assert_eq!(alice.select("BTC".to_owned()).unwrap(), 0);
+        alice.insert(2, 20).unwrap();
+        alice.insert(3, 30).unwrap();
+        assert!(matches!(alice.insert(4, 40), Err(SeriesError::Full(2))));
+        alice.insert(3, 35).unwrap();
+
+        assert_eq!(bob.select("BTC".to_owned()).unwrap(), 2);
+        assert_eq!(bob.read(|prices| prices.summary(0, 10).sum), 55);
+        assert_eq!(alice.select(String::new()).unwrap(), 1);
+
+        // Still there once nobody has it selected, within its retention
+        bob.select(String::new()).unwrap();
+        assert_eq!(alice.select("BTC".to_owned()).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_max_series() {
+        let registry = Arc::new(Registry::new(Limits {
+            retention: Duration::ZERO,
+            max_prices: 16,
+            max_series: 2,
+            max_total_prices: 2,
+        }));
+        let mut alice = Session::new(Arc::clone(&registry));
+        let mut bob = Session::new(Arc::clone(&registry));
+        let mut carol = Session::new(Arc::clone(&registry));
+
+        alice.select("A".to_owned()).unwrap();
+        alice.insert(1, 10).unwrap();
+        alice.insert(2, 20).unwrap();
+        bob.select("B".to_owned()).unwrap();
+        assert!(matches!(
+            bob.insert(1, 10),
+            Err(SeriesError::OutOfPrices(2))
+        ));
+        assert!(matches!(
+            carol.select("C".to_owned()),
+            Err(SeriesError::TooManySeries(2))
+        ));
+
+        // Expired once nobody has it selected, making room
+        alice.select(String::new()).unwrap();
+        registry.sweep();
+        bob.insert(1, 10).unwrap();
+        assert_eq!(carol.select("C".to_owned()).unwrap(), 0);
+        assert!(matches!(
+            alice.select("A".to_owned()),
+            Err(SeriesError::TooManySeries(2))
+        ));
+
+        bob.select(String::new()).unwrap();
+        assert_eq!(alice.select("A".to_owned()).unwrap(), 0);
+    }
+}
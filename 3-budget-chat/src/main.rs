@@ -1,19 +1,26 @@
 mod state;
 
 use futures::{SinkExt, StreamExt};
-use protohackers_utils::default_tcp_listen;
+use protohackers_utils::{
+    default_tcp_listen, ConnectionContext, ConnectionStream, InspectCodec, RateLimit,
+    RateLimitError, RateLimitPolicy, RateLimited,
+};
 use state::{Event, State};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::{net::TcpStream, select, sync::RwLock};
+use std::sync::Arc;
+use tokio::{select, sync::RwLock};
 use tokio_util::codec::{Framed, LinesCodec};
 
 type ClientName = String;
 
 type Message = String;
 
+type ChatFramed = RateLimited<Framed<ConnectionStream, InspectCodec<LinesCodec>>, String>;
+
+const RATE_LIMIT_BURST: u32 = 32;
+const RATE_LIMIT_PER_SECOND: u32 = 8;
+
 async fn handle_joined(
-    mut framed: Framed<TcpStream, LinesCodec>,
-    addr: SocketAddr,
+    mut framed: ChatFramed,
     name: String,
     state: Arc<RwLock<State>>,
 ) -> anyhow::Result<()> {
@@ -25,11 +32,14 @@ async fn handle_joined(
     loop {
         select! {
             item = framed.next() => {
-                #[cfg(debug_assertions)]
-                println!("{addr} --> {item:?}");
-
-                let message = item.ok_or(anyhow::Error::msg("Got EOF"))??;
-                let message = message;
+                let message = match item.ok_or(anyhow::Error::msg("Got EOF"))? {
+                    Ok(message) => message,
+                    Err(err @ RateLimitError::Exceeded) => {
+                        framed.send("* You are sending messages too fast, bye").await?;
+                        return Err(err.into());
+                    }
+                    Err(err) => return Err(err.into()),
+                };
 
                 {
                     let mut state = state.write().await;
@@ -37,7 +47,7 @@ async fn handle_joined(
                 }
             }
             event = receive.recv() => {
-                let event = event.ok_or(anyhow::Error::msg("Somohow all senders dropped?"))?;
+                let event = event?.ok_or(anyhow::Error::msg("Somohow all senders dropped?"))?;
 
                 match event {
                     Event::NewClient(name) => {
@@ -62,11 +72,15 @@ async fn handle_joined(
 }
 
 async fn handle_client(
-    stream: TcpStream,
-    addr: SocketAddr,
+    stream: ConnectionStream,
+    ctx: ConnectionContext,
     state: Arc<RwLock<State>>,
 ) -> anyhow::Result<()> {
-    let mut framed = Framed::new(stream, LinesCodec::new());
+    let mut framed = RateLimited::new(
+        Framed::new(stream, InspectCodec::new(LinesCodec::new(), ctx)),
+        RateLimit::per_second(RATE_LIMIT_BURST, RATE_LIMIT_PER_SECOND),
+        RateLimitPolicy::Close,
+    );
 
     framed
         .send("Welcome to budgetchat! What shall I call you?")
@@ -99,7 +113,7 @@ async fn handle_client(
         .send(format!("* The room contains: {online}"))
         .await?;
 
-    let result = handle_joined(framed, addr, name.clone(), state.clone()).await;
+    let result = handle_joined(framed, name.clone(), state.clone()).await;
 
     {
         let mut state = state.write().await;
@@ -114,9 +128,9 @@ async fn main() -> anyhow::Result<()> {
     // TODO: RwLock?
     let state = Arc::new(RwLock::new(State::default()));
 
-    default_tcp_listen(|stream, addr| {
+    default_tcp_listen(|stream, ctx| {
         let state = Arc::clone(&state);
-        handle_client(stream, addr, state)
+        handle_client(stream, ctx, state)
     })
     .await?;
 
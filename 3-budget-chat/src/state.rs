@@ -1,19 +1,20 @@
 use std::collections::HashMap;
 
-use tokio::sync::mpsc;
+use protohackers_utils::{outbound_queue, OutboundReceiver, OutboundSender, SlowConsumerPolicy};
 
 use crate::{ClientName, Message};
 
+/// Events that can be waiting for a slow client before it gets disconnected. Every message in the
+/// room reaches every client, so this fits eight peers sending their whole rate limit burst at once.
+const OUTBOUND_CAPACITY: usize = 256;
+
 #[derive(Debug, Default)]
 pub struct State {
-    clients: HashMap<ClientName, mpsc::UnboundedSender<Event>>,
+    clients: HashMap<ClientName, OutboundSender<Event>>,
 }
 
 impl State {
-    pub fn add_client(
-        &mut self,
-        name: ClientName,
-    ) -> anyhow::Result<mpsc::UnboundedReceiver<Event>> {
+    pub fn add_client(&mut self, name: ClientName) -> anyhow::Result<OutboundReceiver<Event>> {
         if self.clients.contains_key(&name) {
             return Err(anyhow::Error::msg("Name already taken"));
         }
@@ -22,10 +23,10 @@ impl State {
 
         for sender in self.clients.values() {
             // Okay to ignore errors, just drop them
-            let _ = sender.send(event.clone());
+            let _ = sender.try_send(event.clone());
         }
 
-        let (send, receive) = mpsc::unbounded_channel();
+        let (send, receive) = outbound_queue(OUTBOUND_CAPACITY, SlowConsumerPolicy::Disconnect);
         self.clients.insert(name, send);
 
         Ok(receive)
@@ -44,12 +45,14 @@ impl State {
             }
 
             // Okay to ignore errors, just drop them
-            let _ = sender.send(event.clone());
+            let _ = sender.try_send(event.clone());
         }
     }
 
     pub fn disconnect_client(&mut self, name: ClientName) {
-        self.clients.remove(&name);
+        if let Some(sender) = self.clients.remove(&name) {
+            println!("{name} outbound queue: {}", sender.metrics());
+        }
 
         let event = Event::Disconnect(name.clone());
 
@@ -59,7 +62,7 @@ impl State {
             }
 
             // Okay to ignore errors, just drop them
-            let _ = sender.send(event.clone());
+            let _ = sender.try_send(event.clone());
         }
     }
 }
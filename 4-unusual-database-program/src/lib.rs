@@ -0,0 +1,34 @@
+/// Splits a request into its key and, for inserts, its value. Only the first `=` separates them,
+/// so values may contain `=` too.
+pub fn split_request(message: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match message.iter().position(|&byte| byte == b'=') {
+        Some(index) => (&message[..index], Some(&message[index + 1..])),
+        None => (message, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_request() {
+        assert_eq!(
+            split_request(b"foo=bar"),
+            (b"foo".as_slice(), Some(b"bar".as_slice()))
+        );
+        assert_eq!(
+            split_request(b"foo=bar=baz"),
+            (b"foo".as_slice(), Some(b"bar=baz".as_slice()))
+        );
+        assert_eq!(
+            split_request(b"foo="),
+            (b"foo".as_slice(), Some(b"".as_slice()))
+        );
+        assert_eq!(
+            split_request(b"=foo"),
+            (b"".as_slice(), Some(b"foo".as_slice()))
+        );
+        assert_eq!(split_request(b"foo"), (b"foo".as_slice(), None));
+    }
+}
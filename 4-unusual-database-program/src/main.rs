@@ -1,4 +1,5 @@
-use protohackers_utils::DEFAULT_IPV4_ADDR;
+use protohackers_4_unusual_database_program::split_request;
+use protohackers_utils::{inspect_bytes, Direction, PeerRateLimiter, RateLimit, DEFAULT_IPV4_ADDR};
 use std::{
     collections::HashMap,
     io::{self, Write},
@@ -8,6 +9,9 @@ use tokio::net::UdpSocket;
 
 const MAX_MESSAGE_SIZE: usize = 1000;
 
+const RATE_LIMIT_BURST: u32 = 256;
+const RATE_LIMIT_PER_SECOND: u32 = 128;
+
 const VERSION_KEY: &[u8] = b"version";
 
 const VERSION_VALUE: &[u8] =
@@ -26,21 +30,9 @@ async fn read_request_from_socket(
     let (read, addr) = socket.recv_from(buf).await?;
     let message = buf[..read].to_owned();
 
-    #[cfg(debug_assertions)]
-    {
-        println!("{addr} --> {message:?}");
-
-        if let Ok(message_str) = std::str::from_utf8(&message) {
-            println!("{addr} --> {message_str}");
-        }
-    }
+    inspect_bytes(addr, Direction::Received, &message);
 
-    let mut iter = message.splitn(2, |x| *x == b'=');
-    let Some(key) = iter.next() else {
-        return Ok(None);
-    };
-    let value = iter.next();
-    assert!(iter.next().is_none());
+    let (key, value) = split_request(&message);
 
     Ok(Some(Request {
         from: addr,
@@ -79,14 +71,7 @@ async fn write_request_to_socket(
 
     let message = &buf[..total_len];
 
-    #[cfg(debug_assertions)]
-    {
-        println!("{addr:?} <-- {message:?}");
-
-        if let Ok(message_str) = std::str::from_utf8(&message) {
-            println!("{addr} <-- {message_str}");
-        }
-    }
+    inspect_bytes(addr, Direction::Sent, message);
 
     socket.send_to(message, addr).await?;
 
@@ -100,12 +85,22 @@ async fn main() -> anyhow::Result<()> {
     let mut read_buf = [0u8; MAX_MESSAGE_SIZE];
     let mut write_buf = [0u8; MAX_MESSAGE_SIZE];
     let mut state = HashMap::new();
+    let mut rate_limiter = PeerRateLimiter::new(RateLimit::per_second(
+        RATE_LIMIT_BURST,
+        RATE_LIMIT_PER_SECOND,
+    ));
 
     loop {
         let Some(Request { from, key, value }) = read_request_from_socket(&socket, &mut read_buf).await? else {
             continue;
         };
 
+        // There's no way to report errors, so requests over the limit are dropped
+        if rate_limiter.try_acquire(from).is_err() {
+            println!("Dropping request from {from}: rate limit exceeded");
+            continue;
+        }
+
         match value {
             Some(_) if key == VERSION_KEY => {
                 continue;
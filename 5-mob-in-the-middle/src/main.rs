@@ -1,9 +1,12 @@
 use fancy_regex::Regex;
 use futures::{SinkExt, StreamExt};
 use lazy_static::lazy_static;
-use protohackers_utils::{default_tcp_listen, StrictLinesCodec};
-use std::{borrow::Cow, net::SocketAddr};
-use tokio::{io, net::TcpStream};
+use protohackers_utils::{
+    default_tcp_listen, ConnectionContext, ConnectionStream, InspectCodec, LinesCodecError,
+    StrictLinesCodec,
+};
+use std::{borrow::Cow, env};
+use tokio::net::TcpStream;
 use tokio_util::codec::{FramedRead, FramedWrite};
 
 lazy_static! {
@@ -12,58 +15,63 @@ lazy_static! {
 
 const TARGET_BOGUSCOIN: &str = "7YWHMfk9JZe0LM0g1ZauHuiSxhI";
 
+/// Overrides the upstream chat server, e.g. to run against `protohackers-checker`.
+const UPSTREAM_ADDR_ENV_VAR: &str = "MOB_UPSTREAM_ADDR";
+
+const DEFAULT_UPSTREAM_ADDR: &str = "chat.protohackers.com:16963";
+
 fn hack_boguscoin_message(message: &str) -> Cow<'_, str> {
     REGEX_BOGUSCOIN.replace_all(&message, TARGET_BOGUSCOIN)
 }
 
-async fn handle_client(client_stream: TcpStream, addr: SocketAddr) -> io::Result<()> {
+async fn handle_client(
+    client_stream: ConnectionStream,
+    ctx: ConnectionContext,
+) -> anyhow::Result<()> {
     let (client_read, client_write) = client_stream.into_split();
-    let mut client_read = FramedRead::new(client_read, StrictLinesCodec::new());
-    let mut client_write = FramedWrite::new(client_write, StrictLinesCodec::new());
-
-    let server_stream = TcpStream::connect("chat.protohackers.com:16963").await?;
+    let mut client_read = FramedRead::new(
+        client_read,
+        InspectCodec::new(StrictLinesCodec::new(), &ctx),
+    );
+    let mut client_write = FramedWrite::new(
+        client_write,
+        InspectCodec::new(StrictLinesCodec::new(), &ctx),
+    );
+
+    let upstream_addr =
+        env::var(UPSTREAM_ADDR_ENV_VAR).unwrap_or_else(|_| DEFAULT_UPSTREAM_ADDR.to_owned());
+    let server_stream = TcpStream::connect(upstream_addr).await?;
     let (server_read, server_write) = server_stream.into_split();
     let mut server_read = FramedRead::new(server_read, StrictLinesCodec::new());
     let mut server_write = FramedWrite::new(server_write, StrictLinesCodec::new());
 
-    let client_task = tokio::spawn(async move {
+    let tasks = ctx.task_group::<LinesCodecError>();
+
+    // Either side closing the connection ends the session
+    let session_token = tasks.cancellation_token().clone();
+    tasks.spawn(async move {
         while let Some(message) = client_read.next().await {
-            match message {
-                Ok(message) => {
-                    #[cfg(debug_assertions)]
-                    println!("{addr} --> {message}");
-
-                    server_write.send(hack_boguscoin_message(&message)).await?;
-                }
-                Err(err) => {
-                    // TODO: Abort server task
-                    return Err(err);
-                }
-            }
+            server_write.send(hack_boguscoin_message(&message?)).await?;
         }
 
+        session_token.cancel();
+
         Ok(())
     });
 
-    let server_task = tokio::spawn(async move {
+    let session_token = tasks.cancellation_token().clone();
+    tasks.spawn(async move {
         while let Some(message) = server_read.next().await {
-            match message {
-                Ok(message) => {
-                    #[cfg(debug_assertions)]
-                    println!("{addr} <-- {message}");
-
-                    client_write.send(hack_boguscoin_message(&message)).await?;
-                }
-                Err(err) => {
-                    // TODO: Abort client task
-                    return Err(err);
-                }
-            }
+            client_write.send(hack_boguscoin_message(&message?)).await?;
         }
 
+        session_token.cancel();
+
         Ok(())
     });
 
+    tasks.join().await?;
+
     Ok(())
 }
 
@@ -44,11 +44,6 @@ impl Encoder<MessageToClient> for MessageToClientEncoder {
     fn encode(&mut self, item: MessageToClient, dst: &mut BytesMut) -> Result<(), Self::Error> {
         item.into_bytes_mut(dst);
 
-        #[cfg(debug_assertions)]
-        {
-            println!("{:?}", dst);
-        }
-
         Ok(())
     }
 }
@@ -1,51 +1,64 @@
+use protohackers_utils::{OutboundSender, TaskSpawner};
 use std::time::Duration;
 use thiserror::Error;
-use tokio::{sync::mpsc, task::JoinHandle, time::MissedTickBehavior};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::DropGuard;
 
-use crate::message::MessageToClient;
+use protohackers_6_speed_daemon::message::MessageToClient;
 
-pub struct Heartbeat(Option<JoinHandle<()>>);
+/// Heartbeat task of a connection, stopped when dropped.
+pub struct Heartbeat {
+    spawner: TaskSpawner<anyhow::Error>,
+    guard: Option<DropGuard>,
+}
 
 impl Heartbeat {
-    pub fn new() -> Self {
-        Self(None)
+    pub fn new(spawner: TaskSpawner<anyhow::Error>) -> Self {
+        Self {
+            spawner,
+            guard: None,
+        }
     }
 
     pub fn start(
         &mut self,
-        write: mpsc::Sender<MessageToClient>,
+        write: OutboundSender<MessageToClient>,
         interval: Duration,
     ) -> Result<(), HeartbeatExistsError> {
-        if self.0.is_some() {
+        if self.guard.is_some() {
             return Err(HeartbeatExistsError);
         }
 
-        self.0 = Some(tokio::spawn(async move {
-            if interval.is_zero() {
-                return;
-            }
-
-            let mut interval_f = tokio::time::interval(interval);
-            interval_f.set_missed_tick_behavior(MissedTickBehavior::Delay);
-
-            interval_f.tick().await;
+        let cancellation_token = self.spawner.cancellation_token().child_token();
+        self.guard = Some(cancellation_token.clone().drop_guard());
 
-            loop {
-                interval_f.tick().await;
-                if let Err(_) = write.send(MessageToClient::Heartbeat).await {
-                    return;
-                }
+        self.spawner.spawn(async move {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {}
+                _ = beat(write, interval) => {}
             }
-        }));
+
+            Ok(())
+        });
 
         Ok(())
     }
 }
 
-impl Drop for Heartbeat {
-    fn drop(&mut self) {
-        if let Some(task) = &self.0 {
-            task.abort();
+async fn beat(write: OutboundSender<MessageToClient>, interval: Duration) {
+    if interval.is_zero() {
+        return;
+    }
+
+    let mut interval_f = tokio::time::interval(interval);
+    interval_f.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    interval_f.tick().await;
+
+    loop {
+        interval_f.tick().await;
+        if let Err(_) = write.send(MessageToClient::Heartbeat).await {
+            return;
         }
     }
 }
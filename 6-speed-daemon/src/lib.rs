@@ -0,0 +1,17 @@
+pub mod codec;
+pub mod message;
+pub mod parse;
+
+use std::time::Duration;
+
+pub type Road = u16;
+
+pub type Mile = u16;
+
+pub type Timestamp = u32;
+
+pub type Speed = u16;
+
+pub type Plate = String;
+
+pub type HeartbeatInterval = Duration;
@@ -1,39 +1,30 @@
-mod codec;
 mod heartbeat;
-mod message;
-mod parse;
 mod state;
 
-use codec::{MessageToClientEncoder, MessageToServerDecoder};
 use futures::{SinkExt, Stream, StreamExt};
 use heartbeat::Heartbeat;
-use message::{MessageToClient, MessageToServer};
-use protohackers_utils::default_tcp_listen;
-use state::State;
-use std::{io, net::SocketAddr, sync::Arc, time::Duration};
-use tokio::{
-    io::AsyncWriteExt,
-    net::TcpStream,
-    sync::{mpsc, Mutex},
+use protohackers_6_speed_daemon::{
+    codec::{MessageToClientEncoder, MessageToServerDecoder},
+    message::{MessageToClient, MessageToServer},
+    Mile, Road, Speed,
+};
+use protohackers_utils::{
+    default_tcp_listen, outbound_queue, ConnectionContext, ConnectionId, ConnectionStream,
+    InspectCodec, OutboundReceiver, OutboundSender, SlowConsumerPolicy,
 };
+use state::State;
+use std::{io, sync::Arc};
+use tokio::{io::AsyncWriteExt, net::tcp::OwnedWriteHalf, sync::Mutex};
 use tokio_util::codec::{FramedRead, FramedWrite};
 
-type Road = u16;
-
-type Mile = u16;
-
-type Timestamp = u32;
-
-type Speed = u16;
-
-type Plate = String;
-
-type HeartbeatInterval = Duration;
+/// Messages that can be waiting for a slow client. Once full, heartbeats wait for room and tickets
+/// stay pending with their road, so a dispatcher connecting to a backlog of them gets it as it
+/// reads rather than all at once.
+const OUTBOUND_CAPACITY: usize = 64;
 
 async fn handle_camera(
-    addr: SocketAddr,
     mut read: impl Stream<Item = Result<MessageToServer, io::Error>> + Unpin,
-    write: mpsc::Sender<MessageToClient>,
+    write: OutboundSender<MessageToClient>,
     mut heartbeat: Heartbeat,
     road: Road,
     mile: Mile,
@@ -41,17 +32,10 @@ async fn handle_camera(
     state: Arc<Mutex<State>>,
 ) -> anyhow::Result<()> {
     while let Some(message) = read.next().await {
-        #[cfg(debug_assertions)]
-        {
-            println!("{addr:?} --> [C] {message:?} (road {road}, mile {mile}, limit {limit})");
-        }
-
         match message {
             Ok(MessageToServer::Plate { plate, timestamp }) => {
                 let mut state = state.lock().await;
-                state
-                    .report_plate(road, mile, limit, plate, timestamp)
-                    .await;
+                state.report_plate(road, mile, limit, plate, timestamp);
             }
             Ok(MessageToServer::WantHeartbeat { interval }) => {
                 if let Err(err) = heartbeat.start(write.clone(), interval) {
@@ -73,17 +57,11 @@ async fn handle_camera(
 }
 
 async fn handle_dispatcher_loop(
-    addr: SocketAddr,
     mut read: impl Stream<Item = Result<MessageToServer, io::Error>> + Unpin,
-    write: mpsc::Sender<MessageToClient>,
+    write: OutboundSender<MessageToClient>,
     mut heartbeat: Heartbeat,
 ) -> anyhow::Result<()> {
     while let Some(message) = read.next().await {
-        #[cfg(debug_assertions)]
-        {
-            println!("{addr:?} --> [D] {message:?}");
-        }
-
         match message {
             Ok(MessageToServer::WantHeartbeat { interval }) => {
                 if let Err(err) = heartbeat.start(write.clone(), interval) {
@@ -105,35 +83,81 @@ async fn handle_dispatcher_loop(
 }
 
 async fn handle_dispatcher(
-    addr: SocketAddr,
+    id: ConnectionId,
     read: impl Stream<Item = Result<MessageToServer, io::Error>> + Unpin,
-    write: mpsc::Sender<MessageToClient>,
+    write: OutboundSender<MessageToClient>,
     heartbeat: Heartbeat,
     roads: Vec<Road>,
     state: Arc<Mutex<State>>,
 ) -> anyhow::Result<()> {
     {
         let mut state = state.lock().await;
-        state.insert_dispatcher(&roads, addr, write.clone()).await;
+        state.insert_dispatcher(&roads, id, write.clone());
+    }
+    let _registration = DispatcherRegistration {
+        roads: roads.clone(),
+        id,
+        state: Arc::clone(&state),
+    };
+
+    tokio::select! {
+        res = handle_dispatcher_loop(read, write.clone(), heartbeat) => res,
+        // Only ends once the connection is closed
+        () = deliver_pending(&roads, &write, &state) => Ok(()),
     }
+}
 
-    let res = handle_dispatcher_loop(addr, read, write, heartbeat).await;
+/// Removes a dispatcher from the state when dropped, since its handler is dropped rather than
+/// returning when the connection's writer fails.
+struct DispatcherRegistration {
+    roads: Vec<Road>,
+    id: ConnectionId,
+    state: Arc<Mutex<State>>,
+}
 
-    {
-        let mut state = state.lock().await;
-        state.remove_dispatcher(&roads, addr);
+impl Drop for DispatcherRegistration {
+    fn drop(&mut self) {
+        let roads = std::mem::take(&mut self.roads);
+        let id = self.id;
+        let state = Arc::clone(&self.state);
+
+        // The lock can't be waited for here
+        tokio::spawn(async move {
+            state.lock().await.remove_dispatcher(&roads, id);
+        });
     }
+}
+
+/// Sends the pending tickets of `roads` as the dispatcher makes room for them.
+async fn deliver_pending(
+    roads: &[Road],
+    write: &OutboundSender<MessageToClient>,
+    state: &Mutex<State>,
+) {
+    let pending_added = state.lock().await.pending_added();
+
+    loop {
+        // Created first, so that tickets added while waiting for room aren't missed
+        let pending_added = pending_added.notified();
+
+        write.ready().await;
+        if write.is_closed() {
+            return;
+        }
 
-    res
+        if !state.lock().await.deliver_pending(roads, write) {
+            pending_added.await;
+        }
+    }
 }
 
-async fn handle_err(write: mpsc::Sender<MessageToClient>, message: &str) -> anyhow::Result<()> {
+async fn handle_err(write: OutboundSender<MessageToClient>, message: &str) -> anyhow::Result<()> {
     write.send(MessageToClient::error(message)).await?;
     Ok(())
 }
 
 async fn handle_dynamic_err<T>(
-    write: mpsc::Sender<MessageToClient>,
+    write: OutboundSender<MessageToClient>,
     message: String,
 ) -> anyhow::Result<T> {
     handle_err(write, &message).await?;
@@ -141,7 +165,7 @@ async fn handle_dynamic_err<T>(
 }
 
 async fn handle_forward_err<T, E: Into<anyhow::Error>>(
-    write: mpsc::Sender<MessageToClient>,
+    write: OutboundSender<MessageToClient>,
     err: E,
     message: &'static str,
 ) -> anyhow::Result<T> {
@@ -149,83 +173,110 @@ async fn handle_forward_err<T, E: Into<anyhow::Error>>(
     Err(err.into())
 }
 
-async fn handle_client(
-    stream: TcpStream,
-    addr: SocketAddr,
+async fn handle_messages(
+    mut read: impl Stream<Item = Result<MessageToServer, io::Error>> + Unpin,
+    write: OutboundSender<MessageToClient>,
+    mut heartbeat: Heartbeat,
+    id: ConnectionId,
     state: Arc<Mutex<State>>,
 ) -> anyhow::Result<()> {
-    let (read, write) = stream.into_split();
-    let mut read = FramedRead::new(read, MessageToServerDecoder);
-    let mut write = FramedWrite::new(write, MessageToClientEncoder);
-    let (write_send, mut write_recv) = mpsc::channel::<MessageToClient>(1);
-
-    tokio::spawn(async move {
-        while let Some(message) = write_recv.recv().await {
-            let is_error = message.is_error();
-
-            #[cfg(debug_assertions)]
-            {
-                println!("{addr:?} <-- {message:?}");
-            }
-
-            write.send(message).await?;
-
-            if is_error {
-                write.into_inner().shutdown().await?;
-                break;
-            }
-        }
-
-        Ok::<_, io::Error>(())
-    });
-
-    let mut heartbeat = Heartbeat::new();
-
     while let Some(message) = read.next().await {
-        #[cfg(debug_assertions)]
-        {
-            println!("{addr:?} --> [?] {message:?}");
-        }
-
         match message {
             Ok(MessageToServer::IAmCamera { road, mile, limit }) => {
-                return handle_camera(addr, read, write_send, heartbeat, road, mile, limit, state)
-                    .await
+                return handle_camera(read, write, heartbeat, road, mile, limit, state).await
             }
             Ok(MessageToServer::IAmDispatcher { roads }) => {
-                return handle_dispatcher(addr, read, write_send, heartbeat, roads, state).await
+                return handle_dispatcher(id, read, write, heartbeat, roads, state).await
             }
             Ok(MessageToServer::WantHeartbeat { interval }) => {
-                if let Err(err) = heartbeat.start(write_send.clone(), interval) {
-                    return handle_forward_err(write_send, err, "invalid message").await;
+                if let Err(err) = heartbeat.start(write.clone(), interval) {
+                    return handle_forward_err(write, err, "invalid message").await;
                 }
             }
             Ok(message) => {
                 return handle_dynamic_err(
-                    write_send,
+                    write,
                     format!("expected initial message, got {message:?}"),
                 )
                 .await
             }
-            Err(err) => return handle_forward_err(write_send, err, "invalid message").await,
+            Err(err) => return handle_forward_err(write, err, "invalid message").await,
+        }
+    }
+
+    Ok(())
+}
+
+async fn write_messages(
+    write_recv: &mut OutboundReceiver<MessageToClient>,
+    mut write: FramedWrite<ConnectionStream<OwnedWriteHalf>, InspectCodec<MessageToClientEncoder>>,
+) -> anyhow::Result<()> {
+    while let Some(message) = write_recv.recv().await? {
+        let is_error = message.is_error();
+
+        write.send(message).await?;
+
+        if is_error {
+            write.into_inner().shutdown().await?;
+            break;
         }
     }
 
     Ok(())
 }
 
+async fn handle_client(
+    stream: ConnectionStream,
+    ctx: ConnectionContext,
+    state: Arc<Mutex<State>>,
+) -> anyhow::Result<()> {
+    let (read, write) = stream.into_split();
+    let read = FramedRead::new(read, InspectCodec::new(MessageToServerDecoder, &ctx));
+    let write = FramedWrite::new(write, InspectCodec::new(MessageToClientEncoder, &ctx));
+    let (write_send, mut write_recv) =
+        outbound_queue::<MessageToClient>(OUTBOUND_CAPACITY, SlowConsumerPolicy::Block);
+
+    let tasks = ctx.task_group::<anyhow::Error>();
+
+    // Ends once every sender is dropped, so pending errors are still written after the messages
+    // handling returns
+    tasks.spawn({
+        let ctx = ctx.clone();
+
+        async move {
+            let result = write_messages(&mut write_recv, write).await;
+
+            println!("{ctx} outbound queue: {}", write_recv.metrics());
+
+            result
+        }
+    });
+
+    let heartbeat = Heartbeat::new(tasks.spawner());
+
+    let result = tokio::select! {
+        result = handle_messages(read, write_send, heartbeat, ctx.id(), state) => result,
+        // The writer failed, its error is returned by `join` below
+        _ = tasks.cancelled() => Ok(()),
+    };
+
+    tasks.join().await?;
+
+    result
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let state = Arc::new(Mutex::new(State::new()));
 
-    default_tcp_listen(|stream, addr| {
+    default_tcp_listen(|stream, ctx| {
         let state = Arc::clone(&state);
 
         tokio::spawn(async move {
-            let res = handle_client(stream, addr, state).await;
+            let res = handle_client(stream, ctx.clone(), state).await;
 
             if let Err(err) = res {
-                println!("[ERR] {addr:?} {err:?}");
+                println!("[ERR] {ctx} {err:?}");
             }
         })
     })
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use crate::{MessageToClient, MessageToServer};
+use crate::message::{MessageToClient, MessageToServer};
 use nom::{
     branch::alt,
     bytes::streaming::tag,
@@ -1,13 +1,16 @@
-use crate::{message::MessageToClient, Mile, Plate, Road, Speed, Timestamp};
+use protohackers_6_speed_daemon::{message::MessageToClient, Mile, Plate, Road, Speed, Timestamp};
+use protohackers_utils::{ConnectionId, OutboundSender};
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
-    net::SocketAddr,
+    sync::Arc,
 };
-use tokio::sync::mpsc;
+use tokio::sync::Notify;
 
 pub struct State {
-    dispatchers_by_road: HashMap<Road, HashMap<SocketAddr, mpsc::Sender<MessageToClient>>>,
+    dispatchers_by_road: HashMap<Road, HashMap<ConnectionId, OutboundSender<MessageToClient>>>,
+    /// Tickets no dispatcher had room for, until one does.
     pending_by_road: HashMap<Road, Vec<MessageToClient>>,
+    pending_added: Arc<Notify>,
     cars_seen: HashMap<(Plate, Road), Vec<(Mile, Timestamp)>>,
     ticketed_per_day: HashSet<(Timestamp, Plate)>,
 }
@@ -17,54 +20,77 @@ impl State {
         Self {
             dispatchers_by_road: HashMap::default(),
             pending_by_road: HashMap::default(),
+            pending_added: Arc::new(Notify::new()),
             cars_seen: HashMap::default(),
             ticketed_per_day: HashSet::default(),
         }
     }
 
-    pub async fn insert_dispatcher(
+    pub fn insert_dispatcher(
         &mut self,
         roads: &[Road],
-        addr: SocketAddr,
-        send: mpsc::Sender<MessageToClient>,
+        id: ConnectionId,
+        send: OutboundSender<MessageToClient>,
     ) {
         for road in roads {
-            let Entry::Vacant(v)  = self.dispatchers_by_road
-                .entry(*road)
-                .or_default()
-                .entry(addr) else {
-                    panic!("inserting into existing addr entry")
-                };
+            let Entry::Vacant(v) = self.dispatchers_by_road.entry(*road).or_default().entry(id)
+            else {
+                panic!("inserting into existing connection entry")
+            };
 
             v.insert(send.clone());
+        }
+    }
 
-            for ticket in self.pending_by_road.entry(*road).or_default().drain(..) {
+    /// Notified whenever a ticket is added to the pending ones.
+    pub fn pending_added(&self) -> Arc<Notify> {
+        Arc::clone(&self.pending_added)
+    }
+
+    /// Moves the pending tickets of `roads` to `send` for as long as it has room. Returns whether
+    /// it ran out, so that some may be left.
+    pub fn deliver_pending(
+        &mut self,
+        roads: &[Road],
+        send: &OutboundSender<MessageToClient>,
+    ) -> bool {
+        for road in roads {
+            let Some(pending) = self.pending_by_road.get_mut(road) else {
+                continue;
+            };
+
+            while let Some(ticket) = pending.pop() {
                 #[cfg(debug_assertions)]
                 {
                     println!("Sending pending ticket for road {road}: {ticket:?}");
                 }
 
-                send.send(ticket)
-                    .await
-                    .expect("received non-functioning dispatcher");
+                if let Err(err) = send.try_send(ticket) {
+                    pending.push(err.into_inner());
+                    return true;
+                }
             }
         }
+
+        false
     }
 
-    pub fn remove_dispatcher(&mut self, roads: &[Road], addr: SocketAddr) {
+    pub fn remove_dispatcher(&mut self, roads: &[Road], id: ConnectionId) {
         for road in roads {
             let Entry::Occupied(o) = self
                 .dispatchers_by_road
                 .get_mut(&road)
-                .expect("tried to remove from non-existing road").entry(addr) else {
-                    panic!("removing from non-existing addr entry")
-                };
+                .expect("tried to remove from non-existing road")
+                .entry(id)
+            else {
+                panic!("removing from non-existing connection entry")
+            };
 
             o.remove();
         }
     }
 
-    pub async fn report_plate(
+    pub fn report_plate(
         &mut self,
         road: Road,
         mile: Mile,
@@ -131,25 +157,16 @@ impl State {
                         self.ticketed_per_day.insert((day, plate.clone()));
                     }
 
-                    if let Some(dispatcher) = self
-                        .dispatchers_by_road
-                        .get_mut(&road)
-                        .and_then(|by_road| by_road.values().next())
+                    if let Some(ticket) =
+                        dispatch_ticket(self.dispatchers_by_road.get(&road), ticket.clone())
                     {
-                        dispatcher
-                            .send(ticket.clone())
-                            .await
-                            .expect("a dispatcher send wasn't cleaned up");
-                    } else {
                         #[cfg(debug_assertions)]
                         {
                             println!("Inserting pending ticket for road {road}: {ticket:?}")
                         }
 
-                        self.pending_by_road
-                            .entry(road)
-                            .or_default()
-                            .push(ticket.clone());
+                        self.pending_by_road.entry(road).or_default().push(ticket);
+                        self.pending_added.notify_waiters();
                     }
                 }
             }
@@ -158,3 +175,57 @@ impl State {
         seen.push((mile, timestamp));
     }
 }
+
+/// Hands `ticket` to the first dispatcher accepting it, or gives it back if none did.
+fn dispatch_ticket(
+    dispatchers: Option<&HashMap<ConnectionId, OutboundSender<MessageToClient>>>,
+    mut ticket: MessageToClient,
+) -> Option<MessageToClient> {
+    for dispatcher in dispatchers.into_iter().flat_map(HashMap::values) {
+        match dispatcher.try_send(ticket) {
+            Ok(()) => return None,
+            // That dispatcher's queue is full or closed, try the next one
+            Err(err) => ticket = err.into_inner(),
+        }
+    }
+
+    Some(ticket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protohackers_utils::{outbound_queue, SlowConsumerPolicy};
+
+    #[tokio::test]
+    async fn test_pending_burst() {
+        let mut state = State::new();
+        let ticket = |timestamp2| MessageToClient::Ticket {
+            plate: "UN1X".to_owned(),
+            road: 42,
+            mile1: 0,
+            timestamp1: 0,
+            mile2: 10,
+            timestamp2,
+            speed: 10000,
+        };
+        state
+            .pending_by_road
+            .insert(42, (1..=5).map(ticket).collect());
+
+        // More pending tickets than the queue holds are delivered as it makes room, none lost
+        let (send, mut recv) = outbound_queue(2, SlowConsumerPolicy::Block);
+        state.insert_dispatcher(&[42], 1, send.clone());
+
+        for (left, queued) in [(true, 2), (true, 2), (false, 1)] {
+            assert_eq!(state.deliver_pending(&[42], &send), left);
+            for _ in 0..queued {
+                assert!(matches!(
+                    recv.recv().await.unwrap(),
+                    Some(MessageToClient::Ticket { .. })
+                ));
+            }
+        }
+        assert!(state.pending_by_road[&42].is_empty());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod lrcp;
+
+pub type SessionId = u32;
@@ -5,7 +5,7 @@ use std::{fmt::Debug, io};
 use thiserror::Error;
 
 #[derive(PartialEq, Eq)]
-pub(super) enum LrcpMessage {
+pub enum LrcpMessage {
     Connect {
         session: SessionId,
     },
@@ -45,14 +45,14 @@ impl Debug for LrcpMessage {
 }
 
 impl LrcpMessage {
-    pub(super) fn from<T: AsRef<[u8]>>(input: T) -> Result<LrcpMessage, LrcpMessageError> {
+    pub fn from<T: AsRef<[u8]>>(input: T) -> Result<LrcpMessage, LrcpMessageError> {
         let input = input.as_ref();
         let (_, message) = parser::parse_message(input).map_err(|_| LrcpMessageError::Parse)?;
         Ok(message)
     }
 
     // TODO: Make to_async_writer or similar?
-    pub(super) fn to_vec(&self) -> Vec<u8> {
+    pub fn to_vec(&self) -> Vec<u8> {
         match self {
             LrcpMessage::Connect { session } => format!("/connect/{session}/").into_bytes(),
             LrcpMessage::Data {
@@ -84,7 +84,7 @@ impl LrcpMessage {
         }
     }
 
-    pub(super) fn session_id(&self) -> u32 {
+    pub fn session_id(&self) -> u32 {
         match self {
             LrcpMessage::Connect { session } => *session,
             LrcpMessage::Data { session, .. } => *session,
@@ -95,7 +95,7 @@ impl LrcpMessage {
 }
 
 #[derive(Debug, Error)]
-pub(super) enum LrcpMessageError {
+pub enum LrcpMessageError {
     #[error("error parsing message")]
     Parse,
     #[error("io error on codec: {0}")]
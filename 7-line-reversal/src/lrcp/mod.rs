@@ -1,4 +1,4 @@
-mod message;
+pub mod message;
 mod session_write;
 mod socket;
 mod state;
@@ -6,7 +6,11 @@ mod state;
 use self::session_write::LrcpSessionWrite;
 use crate::SessionId;
 use bytes::Bytes;
-use std::{fmt::Debug, io, net::SocketAddr};
+use std::{
+    fmt::{Debug, Display},
+    io,
+    net::SocketAddr,
+};
 use thiserror::Error;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_util::io::StreamReader;
@@ -51,10 +55,16 @@ pub const MAX_DATA_SIZE: usize = MAX_PACKET_SIZE
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
 pub struct LrcpSessionHandle(SocketAddr, SessionId);
 
-impl Debug for LrcpSessionHandle {
+impl Display for LrcpSessionHandle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let LrcpSessionHandle(addr, session_id) = self;
 
         f.write_fmt(format_args!("{addr}:{session_id}"))
     }
 }
+
+impl Debug for LrcpSessionHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
@@ -3,6 +3,7 @@ use super::{
     state::LrcpState,
     LrcpSessionHandle, LrcpSessionItem, MAX_PACKET_SIZE,
 };
+use protohackers_utils::{inspect_bytes, inspect_item, DatagramSocket, Direction};
 use std::{io, net::SocketAddr, sync::Arc};
 use tokio::{
     net::{ToSocketAddrs, UdpSocket},
@@ -12,7 +13,7 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 
 #[derive(Debug)]
 pub struct LrcpSocket {
-    socket: UdpSocket,
+    socket: Box<dyn DatagramSocket>,
     // TODO: Do I need mutex?
     state: Mutex<LrcpState>,
 }
@@ -24,10 +25,18 @@ impl LrcpSocket {
         addr: A,
     ) -> io::Result<(Self, UnboundedReceiverStream<LrcpSessionItem>)> {
         let socket = UdpSocket::bind(addr).await?;
+
+        Ok(Self::new(socket))
+    }
+
+    /// Runs LRCP over an already bound socket, which may be a simulated one.
+    pub fn new(socket: impl DatagramSocket) -> (Self, UnboundedReceiverStream<LrcpSessionItem>) {
         let (state, recv_session) = LrcpState::new();
         let state = Mutex::new(state);
 
-        Ok((Self { socket, state }, recv_session))
+        let socket = Box::new(socket);
+
+        (Self { socket, state }, recv_session)
     }
 
     // TODO: unpub
@@ -36,32 +45,20 @@ impl LrcpSocket {
         peer: SocketAddr,
         message: &LrcpMessage,
     ) -> io::Result<()> {
-        #[cfg(debug_assertions)]
-        {
-            let handle = LrcpSessionHandle(peer, message.session_id());
+        let handle = LrcpSessionHandle(peer, message.session_id());
 
-            println!("{handle:?} <-- {message:?}");
-        }
+        inspect_item(handle, Direction::Sent, message);
 
         let packet = message.to_vec();
 
+        inspect_bytes(handle, Direction::Sent, &packet);
+
         let written = self.socket.send_to(&packet, peer).await?;
 
         if written != packet.len() {
             return Err(io::Error::new(io::ErrorKind::Other, "Not all bytes sent"));
         }
 
-        #[cfg(debug_assertions)]
-        {
-            /*
-            println!("{peer} <-- {packet:?}");
-
-            if let Ok(reply_packet_str) = std::str::from_utf8(&packet) {
-                println!("{peer} <-- {reply_packet_str}");
-            }
-            */
-        }
-
         Ok(())
     }
 
@@ -164,24 +161,12 @@ impl LrcpSocket {
 // TODO: Drop? TcpStream does not have `close`
 
 fn receive(peer: SocketAddr, packet: &[u8]) -> Result<LrcpMessage, LrcpMessageError> {
-    #[cfg(debug_assertions)]
-    {
-        /*
-        println!("{peer} --> {packet:?}");
-
-        if let Ok(packet_str) = std::str::from_utf8(packet) {
-            println!("{peer} --> {packet_str}");
-        }
-        */
-    }
-
     let message = LrcpMessage::from(packet)?;
 
-    #[cfg(debug_assertions)]
-    {
-        let handle = LrcpSessionHandle(peer, message.session_id());
-        println!("{handle:?} --> {message:?}");
-    }
+    let handle = LrcpSessionHandle(peer, message.session_id());
+
+    inspect_item(handle, Direction::Received, &message);
+    inspect_bytes(handle, Direction::Received, packet);
 
     Ok(message)
 }
@@ -2,10 +2,7 @@ use super::{
     session_write::{LrcpSessionBuffer, LrcpSessionWrite},
     LrcpSocket,
 };
-use crate::{
-    lrcp::{LrcpSessionError, LrcpSessionItem, LrcpSessionReadItem},
-    LrcpSessionHandle,
-};
+use crate::lrcp::{LrcpSessionError, LrcpSessionHandle, LrcpSessionItem, LrcpSessionReadItem};
 use bytes::Bytes;
 use std::{
     collections::{hash_map::Entry, HashMap},
@@ -1,49 +1,44 @@
-#![feature(array_windows)]
-
-mod lrcp;
-
 use futures::SinkExt;
-use lrcp::{LrcpSessionHandle, LrcpSocket};
-use protohackers_utils::DEFAULT_IPV4_ADDR;
+use protohackers_7_line_reversal::lrcp::{LrcpSessionHandle, LrcpSessionItem, LrcpSocket};
+use protohackers_utils::{DelimitedBytesCodec, InspectCodec, DEFAULT_IPV4_ADDR};
 use std::sync::Arc;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     task::JoinHandle,
 };
-use tokio_stream::StreamExt;
-use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
-
-type SessionId = u32;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use tokio_util::codec::{FramedRead, FramedWrite};
 
 async fn handle_client(
     session: LrcpSessionHandle,
     read: impl AsyncRead + Unpin,
     write: impl AsyncWrite + Unpin,
 ) -> Result<(), anyhow::Error> {
-    let mut read_framed = FramedRead::new(read, LinesCodec::new());
-    let mut write_framed = FramedWrite::new(write, LinesCodec::new());
+    let mut read_framed = FramedRead::new(
+        read,
+        InspectCodec::new(DelimitedBytesCodec::new(b'\n'), session),
+    );
+    let mut write_framed = FramedWrite::new(
+        write,
+        InspectCodec::new(DelimitedBytesCodec::new(b'\n'), session),
+    );
 
     while let Some(item) = read_framed.next().await {
-        #[cfg(debug_assertions)]
-        println!("{session:?} ==> {item:?}");
-
         let item = item?;
 
-        let reverse = item.chars().rev().collect::<String>().into_bytes();
-        let reverse_str = std::str::from_utf8(&reverse)?;
+        let reverse = item.iter().rev().copied().collect::<Vec<u8>>();
 
-        println!("{session:?} <== {reverse_str:?}");
-
-        write_framed.send(reverse_str).await?;
+        write_framed.send(reverse).await?;
     }
 
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // TODO: Make listener?
-    let (socket, mut recv_session) = LrcpSocket::bind(DEFAULT_IPV4_ADDR).await?;
+/// Serves the sessions of `socket` until receiving from it fails.
+async fn serve(
+    socket: LrcpSocket,
+    mut recv_session: UnboundedReceiverStream<LrcpSessionItem>,
+) -> anyhow::Result<()> {
     let socket = Arc::new(socket);
 
     // TODO  Handle errors, timeouts, etc. not only data
@@ -72,3 +67,103 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // TODO: Make listener?
+    let (socket, recv_session) = LrcpSocket::bind(DEFAULT_IPV4_ADDR).await?;
+
+    serve(socket, recv_session).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protohackers_7_line_reversal::lrcp::MAX_PACKET_SIZE;
+    use protohackers_utils::run_seeded;
+    use std::{net::SocketAddr, time::Duration};
+    use turmoil::net::UdpSocket;
+
+    const SESSION: u32 = 12345;
+    const CLIENT_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// A minimal LRCP client, retransmitting until everything it sent is acked and `expected` is
+    /// received.
+    async fn lrcp_client(server: SocketAddr, sent: &str, expected: &str) -> turmoil::Result {
+        let socket = UdpSocket::bind("0.0.0.0:1337").await?;
+        let mut buf = [0; MAX_PACKET_SIZE];
+
+        let mut connected = false;
+        let mut acked = 0;
+        let mut received = String::new();
+
+        while !connected || acked < sent.len() || received.len() < expected.len() {
+            if !connected {
+                let connect = format!("/connect/{SESSION}/");
+                socket.send_to(connect.as_bytes(), server).await?;
+            } else if acked < sent.len() {
+                let data = format!("/data/{SESSION}/{acked}/{}/", &sent[acked..]);
+                socket.send_to(data.as_bytes(), server).await?;
+            }
+
+            let Ok(recv) =
+                tokio::time::timeout(CLIENT_RETRANSMISSION_TIMEOUT, socket.recv_from(&mut buf))
+                    .await
+            else {
+                continue;
+            };
+            let (len, _) = recv?;
+            let packet = std::str::from_utf8(&buf[..len])?;
+
+            match packet.splitn(5, '/').collect::<Vec<_>>()[..] {
+                ["", "ack", _, length, ""] => {
+                    connected = true;
+                    acked = acked.max(length.parse()?);
+                }
+                ["", "data", _, position, data] => {
+                    if position.parse::<usize>()? == received.len() {
+                        received.push_str(data.trim_end_matches('/'));
+                    }
+
+                    let ack = format!("/ack/{SESSION}/{}/", received.len());
+                    socket.send_to(ack.as_bytes(), server).await?;
+                }
+                _ => return Err(format!("unexpected packet {packet:?}").into()),
+            }
+        }
+
+        assert_eq!(received, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lossy_network() {
+        run_seeded(0..16, |seed| {
+            let mut sim = turmoil::Builder::new()
+                .rng_seed(seed)
+                .enable_random_order()
+                // Under the session expiry timeout, sessions never expire
+                .simulation_duration(Duration::from_secs(50))
+                .max_message_latency(Duration::from_millis(50))
+                .fail_rate(0.02)
+                .repair_rate(0.05)
+                .build();
+
+            sim.host("server", || async {
+                let socket = turmoil::net::UdpSocket::bind(DEFAULT_IPV4_ADDR).await?;
+                let (socket, recv_session) = LrcpSocket::new(socket);
+
+                Ok(serve(socket, recv_session).await?)
+            });
+
+            sim.client("client", async {
+                let server = SocketAddr::new(turmoil::lookup("server"), 1337);
+
+                lrcp_client(server, "hello\nworld\nlrcp\n", "olleh\ndlrow\npcrl\n").await
+            });
+
+            sim.run()
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use protohackers_utils::Transform;
 use std::num::Wrapping;
 
 pub trait Cipher {
@@ -180,6 +181,16 @@ impl Cipher for ComposedCipher {
     }
 }
 
+impl Transform for ComposedCipher {
+    fn transform_read_byte(&mut self, byte: u8) -> u8 {
+        self.decipher(byte)
+    }
+
+    fn transform_write_byte(&mut self, byte: u8) -> u8 {
+        self.cipher(byte)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
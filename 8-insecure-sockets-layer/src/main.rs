@@ -1,64 +1,51 @@
-mod cipher;
-mod codec;
-
-use bytes::Bytes;
-use cipher::{Cipher, ComposedCipher};
-use codec::CipherEncoder;
 use futures::{SinkExt, StreamExt};
-use protohackers_utils::default_tcp_listen;
-use std::net::SocketAddr;
-use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    net::TcpStream,
-};
-use tokio_util::{
-    codec::{FramedRead, FramedWrite, LinesCodec},
-    io::{ReaderStream, StreamReader},
+use protohackers_8_insecure_sockets_layer::cipher::ComposedCipher;
+use protohackers_utils::{
+    default_tcp_listen, ConnectionContext, ConnectionStream, DelimitedBytesCodec, InspectCodec,
+    TransformStream,
 };
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_util::codec::{FramedRead, FramedWrite};
 
-async fn handle_client(mut stream: TcpStream, addr: SocketAddr) -> anyhow::Result<()> {
+async fn handle_client(mut stream: ConnectionStream, ctx: ConnectionContext) -> anyhow::Result<()> {
     let (read, write) = stream.split();
 
-    let (read, cipher_spec) = {
-        let mut read = BufReader::new(read);
+    // Keep the `BufReader` around since it might have buffered data past the cipher spec
+    let mut read = BufReader::new(read);
+
+    let cipher_spec = {
         let mut cipher_spec = Vec::new();
 
         read.read_until(0x00, &mut cipher_spec).await?;
 
-        (read.into_inner(), cipher_spec)
+        cipher_spec
     };
 
     let ciphers = ComposedCipher::from_spec_slice(&cipher_spec[..cipher_spec.len() - 1])
         .ok_or(anyhow::Error::msg("Invalid cipher spec"))?;
 
-    println!("{addr} wants ciphers: {ciphers:?}");
+    println!("{ctx} wants ciphers: {ciphers:?}");
 
     if ciphers.check_is_noop() {
         return Err(anyhow::Error::msg("Tried to use a no-op cipher"));
     }
 
-    let mut read_cipher = ciphers.clone();
+    let read = TransformStream::new(read, ciphers.clone());
+    let write = TransformStream::new(write, ciphers);
 
-    let read = ReaderStream::new(read).map(|item| {
-        item.map(|bytes| {
-            bytes
-                .iter()
-                .map(|byte| read_cipher.decipher(*byte))
-                .collect::<Bytes>()
-        })
-    });
+    let mut read_framed = FramedRead::new(
+        read,
+        InspectCodec::new(DelimitedBytesCodec::new(b'\n'), &ctx),
+    );
 
-    let read = StreamReader::new(read);
-
-    let mut read_framed = FramedRead::new(read, LinesCodec::new());
-
-    let mut write_framed = FramedWrite::new(write, CipherEncoder::new(LinesCodec::new(), ciphers));
+    let mut write_framed = FramedWrite::new(
+        write,
+        InspectCodec::new(DelimitedBytesCodec::new(b'\n'), &ctx),
+    );
 
     while let Some(item) = read_framed.next().await {
-        #[cfg(debug_assertions)]
-        println!("{addr} --> {item:?}");
-
         let item = item?;
+        let item = std::str::from_utf8(&item)?;
 
         let parsed_items = item
             .split(",")
@@ -1,14 +1,17 @@
 use serde::Serialize;
+use serde_json::value::RawValue;
+use std::sync::Arc;
 
 use crate::state::QueueName;
 
 pub type JobId = u64;
 
-pub type JobValue = serde_json::Value;
+/// Job bodies are kept as received, so they are never re-serialized and are cheap to clone.
+pub type JobValue = Arc<RawValue>;
 
 pub type JobPriority = u64;
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct FullJob {
     id: JobId,
     #[serde(rename = "job")]
@@ -1,4 +1,3 @@
-// TODO: Zero copy serde?
 // TODO: Is it still leakingo on hard closes?
 
 mod job;
@@ -11,110 +10,142 @@ use crate::{
     response::Response,
     state::{State, WaitResponse},
 };
-use futures::{SinkExt, StreamExt};
-use protohackers_utils::{default_tcp_listen, framed_json, JsonCodecError};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::{net::TcpStream, sync::Mutex};
+use futures::SinkExt;
+use protohackers_utils::{
+    default_tcp_listen, framed_json, serve_framed, ConnectionContext, ConnectionId,
+    ConnectionStream, InspectCodec, JsonFrame, RateLimit, RateLimitError, RateLimitPolicy,
+    RateLimited, ServeError,
+};
+use serde_json::value::RawValue;
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::Mutex,
+};
+use tower::service_fn;
+
+/// Generous enough for the load tests, a client going past it is misbehaving.
+const RATE_LIMIT_BURST: u32 = 4096;
+const RATE_LIMIT_PER_SECOND: u32 = 2048;
 
 // TODO: tokio_serde_json?
 
-async fn handle_client(
-    stream: TcpStream,
-    addr: SocketAddr,
+async fn handle_request(
+    frame: JsonFrame,
+    client: ConnectionId,
     state: Arc<Mutex<State>>,
-) -> anyhow::Result<()> {
-    let mut framed = framed_json(stream);
+) -> anyhow::Result<Option<Response>> {
+    let response = match frame.deserialize::<Request>() {
+        Ok(Request::Put { queue, job, pri }) => {
+            let job = Arc::<RawValue>::from(job.to_owned());
 
-    while let Some(item) = framed.next().await {
-        #[cfg(debug_assertions)]
-        println!("{addr} --> {item:?}");
+            let id = {
+                let mut state = state.lock().await;
+                state.add_job(queue, job, pri)
+            };
 
-        let response = match item {
-            Ok(Request::Put { queue, job, pri }) => {
-                let id = {
+            Response::ok_put(id)
+        }
+        Ok(Request::Get { queues, wait }) => {
+            if !wait {
+                let get_job_response = {
                     let mut state = state.lock().await;
-                    state.add_job(queue, job, pri)
+                    state.get_job(client, queues)
                 };
 
-                Response::ok_put(id)
-            }
-            Ok(Request::Get { queues, wait }) => {
-                if !wait {
-                    let get_job_response = {
-                        let mut state = state.lock().await;
-                        state.get_job(addr, queues)
-                    };
-
-                    match get_job_response {
-                        Some(full_job) => Response::ok_get(full_job),
-                        None => Response::NoJob,
-                    }
-                } else {
-                    let wait_response = {
-                        let mut state = state.lock().await;
-                        state.wait_job(addr, queues)
-                    };
-
-                    let full_job = match wait_response {
-                        WaitResponse::Job(full_job) => full_job,
-                        WaitResponse::Wait(receiver) => receiver.await?,
-                    };
-
-                    Response::ok_get(full_job)
+                match get_job_response {
+                    Some(full_job) => Response::ok_get(full_job),
+                    None => Response::NoJob,
                 }
-            }
-            Ok(Request::Delete { id }) => {
-                let delete_response = {
+            } else {
+                let wait_response = {
                     let mut state = state.lock().await;
-                    state.delete_job(id)
+                    state.wait_job(client, queues)
                 };
 
-                if delete_response {
-                    Response::ok_delete()
-                } else {
-                    Response::no_job()
-                }
-            }
-            Ok(Request::Abort { id }) => {
-                let abort_response = {
-                    let mut state = state.lock().await;
-                    state.abort_job(addr, id)
+                let full_job = match wait_response {
+                    WaitResponse::Job(full_job) => full_job,
+                    WaitResponse::Wait(receiver) => receiver.await?,
                 };
 
-                if abort_response {
-                    Response::ok_abort()
-                } else {
-                    Response::no_job()
-                }
+                Response::ok_get(full_job)
             }
-            #[cfg(debug_assertions)]
-            Ok(Request::Debug) => {
-                {
-                    let state = state.lock().await;
-                    println!("{state:#?}");
-                }
+        }
+        Ok(Request::Delete { id }) => {
+            let delete_response = {
+                let mut state = state.lock().await;
+                state.delete_job(id)
+            };
 
-                Response::ok_debug()
-            }
-            Err(JsonCodecError::SerdeJson(_)) => Response::error("Invalid request".to_string()),
-            Err(err @ JsonCodecError::Io(_) | err @ JsonCodecError::LinesCodec(_)) => {
-                return Err(err.into())
+            if delete_response {
+                Response::ok_delete()
+            } else {
+                Response::no_job()
             }
-        };
+        }
+        Ok(Request::Abort { id }) => {
+            let abort_response = {
+                let mut state = state.lock().await;
+                state.abort_job(client, id)
+            };
 
+            if abort_response {
+                Response::ok_abort()
+            } else {
+                Response::no_job()
+            }
+        }
         #[cfg(debug_assertions)]
-        println!("{addr} <-- {response:?}");
+        Ok(Request::Debug) => {
+            {
+                let state = state.lock().await;
+                println!("{state:#?}");
+            }
 
-        framed.send(&response).await?;
-    }
+            Response::ok_debug()
+        }
+        Err(_) => Response::error("Invalid request".to_string()),
+    };
 
-    println!("Client {addr} disconnected");
+    Ok(Some(response))
+}
+
+async fn handle_client<S>(
+    stream: ConnectionStream<S>,
+    ctx: ConnectionContext,
+    state: Arc<Mutex<State>>,
+) -> anyhow::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let framed = framed_json::<_, JsonFrame, Response>(stream)
+        .map_codec(|codec| InspectCodec::new(codec, &ctx));
+    let mut framed = RateLimited::new(
+        framed,
+        RateLimit::per_second(RATE_LIMIT_BURST, RATE_LIMIT_PER_SECOND),
+        RateLimitPolicy::Close,
+    );
 
+    let service = service_fn(|frame| handle_request(frame, ctx.id(), Arc::clone(&state)));
+
+    let result = match serve_framed(&mut framed, service).await {
+        Err(ServeError::Decode(err @ RateLimitError::Exceeded)) => {
+            framed
+                .send(Response::error("rate limit exceeded".to_owned()))
+                .await?;
+            Err(err.into())
+        }
+        result => result.map_err(anyhow::Error::from),
+    };
+
+    // Release the jobs even if the connection ended on an error
     {
         let mut state = state.lock().await;
-        state.abort_client_jobs(addr);
+        state.abort_client_jobs(ctx.id());
     }
 
+    result?;
+
     Ok(())
 }
 
@@ -123,11 +154,106 @@ async fn main() -> anyhow::Result<()> {
     // TODO: RwLock?
     let state = Arc::new(Mutex::new(State::default()));
 
-    default_tcp_listen(|stream, addr| {
+    default_tcp_listen(|stream, ctx| {
         let state = Arc::clone(&state);
-        handle_client(stream, addr, state)
+        handle_client(stream, ctx, state)
     })
     .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protohackers_utils::{listen, run_seeded, DEFAULT_IPV4_ADDR};
+    use serde_json::{json, Value};
+    use std::{io, time::Duration};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use turmoil::net::{TcpListener, TcpStream};
+
+    type Client = BufReader<TcpStream>;
+
+    async fn connect() -> turmoil::Result<Client> {
+        // The server may not be listening yet
+        loop {
+            match TcpStream::connect(("server", 1337)).await {
+                Ok(stream) => return Ok(BufReader::new(stream)),
+                Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                    tokio::time::sleep(Duration::from_millis(10)).await
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    async fn request(client: &mut Client, request: Value) -> turmoil::Result<Value> {
+        client.write_all(format!("{request}\n").as_bytes()).await?;
+
+        let mut line = String::new();
+        client.read_line(&mut line).await?;
+
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    async fn get_wait(client: &mut Client) -> turmoil::Result<Value> {
+        let response = request(
+            client,
+            json!({"request": "get", "queues": ["queue"], "wait": true}),
+        )
+        .await?;
+
+        Ok(response["id"].clone())
+    }
+
+    #[test]
+    fn test_wait_abort_races() {
+        run_seeded(0..32, |seed| {
+            let mut sim = turmoil::Builder::new()
+                .rng_seed(seed)
+                .enable_random_order()
+                .simulation_duration(Duration::from_secs(10))
+                .max_message_latency(Duration::from_millis(20))
+                .build();
+
+            sim.host("server", || async {
+                let state = Arc::new(Mutex::new(State::default()));
+                let listener = TcpListener::bind(DEFAULT_IPV4_ADDR).await?;
+
+                listen(listener, |stream, ctx| {
+                    handle_client(stream, ctx, Arc::clone(&state))
+                })
+                .await?;
+
+                Ok(())
+            });
+
+            sim.client("clients", async {
+                let mut producer = connect().await?;
+                let mut worker1 = connect().await?;
+                let mut worker2 = connect().await?;
+
+                // The put may reach the server before or after the wait
+                let put = json!({"request": "put", "queue": "queue", "job": {}, "pri": 1});
+                let (id, _) = tokio::join!(get_wait(&mut worker1), request(&mut producer, put));
+                let id = id?;
+
+                // The abort may hand the job to the waiting worker, or requeue it for it
+                let abort = json!({"request": "abort", "id": id});
+                let (id2, aborted) =
+                    tokio::join!(get_wait(&mut worker2), request(&mut worker1, abort));
+                assert_eq!(aborted?["status"], "ok");
+                assert_eq!(id2?, id);
+
+                // Disconnecting gives the job back too
+                drop(worker2);
+                let mut worker3 = connect().await?;
+                assert_eq!(get_wait(&mut worker3).await?, id);
+
+                Ok(())
+            });
+
+            sim.run()
+        });
+    }
+}
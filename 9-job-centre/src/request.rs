@@ -1,20 +1,20 @@
 use crate::{
-    job::{JobId, JobPriority, JobValue},
+    job::{JobId, JobPriority},
     state::QueueName,
 };
-use serde::Deserialize;
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::value::RawValue;
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
-#[serde(tag = "request", rename_all = "kebab-case")]
-pub enum Request {
+/// A request borrowing its job body from the received line.
+#[derive(Debug)]
+pub enum Request<'a> {
     Put {
         queue: QueueName,
-        job: JobValue,
+        job: &'a RawValue,
         pri: JobPriority,
     },
     Get {
         queues: Vec<QueueName>,
-        #[serde(default)]
         wait: bool,
     },
     Delete {
@@ -26,3 +26,56 @@ pub enum Request {
     #[cfg(debug_assertions)]
     Debug,
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RequestType {
+    Put,
+    Get,
+    Delete,
+    Abort,
+    #[cfg(debug_assertions)]
+    Debug,
+}
+
+/// Flat version of [`Request`], since `RawValue` cannot be borrowed through internally tagged enums.
+#[derive(Debug, Deserialize)]
+struct RawRequest<'a> {
+    request: RequestType,
+    queue: Option<QueueName>,
+    #[serde(borrow)]
+    job: Option<&'a RawValue>,
+    pri: Option<JobPriority>,
+    queues: Option<Vec<QueueName>>,
+    #[serde(default)]
+    wait: bool,
+    id: Option<JobId>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Request<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawRequest::deserialize(deserializer)?;
+
+        match raw.request {
+            RequestType::Put => Ok(Request::Put {
+                queue: raw.queue.ok_or_else(|| D::Error::missing_field("queue"))?,
+                job: raw.job.ok_or_else(|| D::Error::missing_field("job"))?,
+                pri: raw.pri.ok_or_else(|| D::Error::missing_field("pri"))?,
+            }),
+            RequestType::Get => Ok(Request::Get {
+                queues: raw
+                    .queues
+                    .ok_or_else(|| D::Error::missing_field("queues"))?,
+                wait: raw.wait,
+            }),
+            RequestType::Delete => Ok(Request::Delete {
+                id: raw.id.ok_or_else(|| D::Error::missing_field("id"))?,
+            }),
+            RequestType::Abort => Ok(Request::Abort {
+                id: raw.id.ok_or_else(|| D::Error::missing_field("id"))?,
+            }),
+            #[cfg(debug_assertions)]
+            RequestType::Debug => Ok(Request::Debug),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::job::{FullJob, JobId};
 use serde::Serialize;
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Serialize)]
 #[serde(tag = "status", rename_all = "kebab-case")]
 pub enum Response {
     Ok(OkResponse),
@@ -54,7 +54,7 @@ impl AsRef<Response> for Response {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum OkResponse {
     Put {
@@ -2,9 +2,10 @@ use priority_queue::PriorityQueue;
 use tokio::sync::oneshot::{self, Receiver, Sender};
 
 use crate::job::{FullJob, JobId, JobPriority, JobValue};
+use protohackers_utils::ConnectionId;
 use std::{
     collections::{hash_map::Entry, HashMap},
-    net::SocketAddr,
+    sync::Arc,
 };
 
 pub type QueueName = String;
@@ -22,9 +23,9 @@ pub struct State {
     jobs_by_id: HashMap<JobId, (JobValue, QueueName)>,
     queues: HashMap<QueueName, Queue>,
     // TODO: Clear these ones on delete?
-    working_jobs_by_client: HashMap<SocketAddr, HashMap<JobId, WorkingJob>>,
+    working_jobs_by_client: HashMap<ConnectionId, HashMap<JobId, WorkingJob>>,
     // TODO: Clear these ones on disconnect?
-    waiting: Vec<(Vec<QueueName>, Sender<FullJob>)>,
+    waiting: Vec<(ConnectionId, Vec<QueueName>, Sender<FullJob>)>,
 }
 
 impl State {
@@ -38,14 +39,18 @@ impl State {
 
         // TODO: Clone
         self.jobs_by_id
-            .insert(job_id, (job_value.clone(), queue_name.clone()));
+            .insert(job_id, (Arc::clone(&job_value), queue_name.clone()));
 
         self.add_existing_job_to_queue(job_id, queue_name, job_value, priority);
 
         job_id
     }
 
-    pub fn get_job(&mut self, client: SocketAddr, queue_names: Vec<QueueName>) -> Option<FullJob> {
+    pub fn get_job(
+        &mut self,
+        client: ConnectionId,
+        queue_names: Vec<QueueName>,
+    ) -> Option<FullJob> {
         // TODO: This is bugged because it can get a None here but next one might have lower prio
         let max_peeked = queue_names
             .iter()
@@ -82,13 +87,13 @@ impl State {
 
         Some(FullJob::new(
             job_id,
-            job_value.clone(),
+            Arc::clone(job_value),
             job_priority,
             max_queue_name.to_owned(),
         ))
     }
 
-    pub fn wait_job(&mut self, client: SocketAddr, queue_names: Vec<QueueName>) -> WaitResponse {
+    pub fn wait_job(&mut self, client: ConnectionId, queue_names: Vec<QueueName>) -> WaitResponse {
         // TODO: Dont' clone
         match self.get_job(client, queue_names.clone()) {
             Some(full_job) => WaitResponse::Job(full_job),
@@ -96,7 +101,7 @@ impl State {
                 // TODO: What if the client dropped?
                 let (sender, receiver) = oneshot::channel::<FullJob>();
 
-                self.waiting.push((queue_names, sender));
+                self.waiting.push((client, queue_names, sender));
 
                 WaitResponse::Wait(receiver)
             }
@@ -117,7 +122,7 @@ impl State {
         true
     }
 
-    pub fn abort_job(&mut self, client: SocketAddr, job_id: JobId) -> bool {
+    pub fn abort_job(&mut self, client: ConnectionId, job_id: JobId) -> bool {
         let Some((job_value, queue_name)) = self.jobs_by_id.get(&job_id) else {
             return false;
         };
@@ -132,13 +137,12 @@ impl State {
 
         let (job_priority, queue_name) = entry.remove();
 
-        // TODO: Clone
-        self.add_existing_job_to_queue(job_id, queue_name, job_value.clone(), job_priority);
+        self.add_existing_job_to_queue(job_id, queue_name, Arc::clone(job_value), job_priority);
 
         true
     }
 
-    pub fn abort_client_jobs(&mut self, client: SocketAddr) {
+    pub fn abort_client_jobs(&mut self, client: ConnectionId) {
         let Entry::Occupied(working_jobs) = self.working_jobs_by_client.entry(client) else {
             return;
         };
@@ -148,8 +152,7 @@ impl State {
                 continue;
             };
 
-            // TODO: Clone
-            self.add_existing_job_to_queue(job_id, queue_name, job_value.clone(), job_priority);
+            self.add_existing_job_to_queue(job_id, queue_name, Arc::clone(job_value), job_priority);
         }
     }
 
@@ -168,23 +171,32 @@ impl State {
         priority: JobPriority,
     ) {
         // TODO: Make map-ish
-        if let Some(index) = self
+        while let Some(index) = self
             .waiting
             .iter()
-            .position(|(queue_names, _)| queue_names.contains(&queue_name))
+            .position(|(_, queue_names, _)| queue_names.contains(&queue_name))
         {
-            let (_, sender) = self.waiting.swap_remove(index);
-
-            sender
-                .send(FullJob::new(job_id, job_value, priority, queue_name))
-                // TODO: Panic
-                .unwrap()
-        } else {
-            self.queues
-                .entry(queue_name)
-                .or_default()
-                .push(job_id, priority);
+            let (client, _, sender) = self.waiting.swap_remove(index);
+
+            let full_job =
+                FullJob::new(job_id, Arc::clone(&job_value), priority, queue_name.clone());
+
+            // The waiting client may be gone already, try the next one
+            if sender.send(full_job).is_ok() {
+                // The client is working on it now, so aborts and disconnections put it back
+                self.working_jobs_by_client
+                    .entry(client)
+                    .or_default()
+                    .insert(job_id, (priority, queue_name));
+
+                return;
+            }
         }
+
+        self.queues
+            .entry(queue_name)
+            .or_default()
+            .push(job_id, priority);
     }
 }
 
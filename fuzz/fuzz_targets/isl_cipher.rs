@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protohackers_8_insecure_sockets_layer::cipher::{Cipher, ComposedCipher};
+
+fuzz_target!(|data: &[u8]| {
+    // A spec length, the spec, and some data to cipher with it
+    let Some((&spec_len, data)) = data.split_first() else {
+        return;
+    };
+    let (spec, payload) = data.split_at(data.len().min(spec_len.into()));
+
+    let Some(cipher) = ComposedCipher::from_spec_slice(spec) else {
+        return;
+    };
+
+    cipher.check_is_noop();
+
+    let (mut encoder, mut decoder) = (cipher.clone(), cipher);
+    let ciphered = payload
+        .iter()
+        .map(|&byte| encoder.cipher(byte))
+        .collect::<Vec<_>>();
+    let deciphered = ciphered
+        .iter()
+        .map(|&byte| decoder.decipher(byte))
+        .collect::<Vec<_>>();
+
+    assert_eq!(deciphered, payload);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protohackers_7_line_reversal::lrcp::message::LrcpMessage;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(message) = LrcpMessage::from(data) else {
+        return;
+    };
+
+    // Not byte for byte, e.g. numbers can have leading zeros
+    let encoded = message.to_vec();
+    assert_eq!(LrcpMessage::from(&encoded).unwrap(), message);
+});
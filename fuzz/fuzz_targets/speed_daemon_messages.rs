@@ -0,0 +1,20 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use protohackers_6_speed_daemon::{codec::MessageToServerDecoder, parse::parse_message_to_client};
+use protohackers_fuzz::{chunks, decode_chunks};
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(
+        decode_chunks(MessageToServerDecoder, &[data]),
+        decode_chunks(MessageToServerDecoder, &chunks(data)),
+    );
+
+    if let Ok((rest, message)) = parse_message_to_client(data) {
+        let mut encoded = BytesMut::new();
+        message.into_bytes_mut(&mut encoded);
+
+        assert_eq!(encoded, data[..data.len() - rest.len()]);
+    }
+});
@@ -0,0 +1,33 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use protohackers_fuzz::{chunks, decode_chunks};
+use protohackers_utils::StrictLinesCodec;
+use tokio_util::codec::Encoder;
+
+const MAX_LENGTH: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    for codec in [
+        StrictLinesCodec::new(),
+        StrictLinesCodec::new_with_max_length(MAX_LENGTH),
+    ] {
+        let decoded = decode_chunks(codec.clone(), &[data]);
+        assert_eq!(decoded, decode_chunks(codec.clone(), &chunks(data)));
+
+        // A trailing '\r' is taken as part of a CRLF line ending, so those can't round trip
+        let (lines, _) = decoded;
+        let lines = lines
+            .into_iter()
+            .filter(|line| !line.ends_with('\r'))
+            .collect::<Vec<_>>();
+
+        let mut encoded = BytesMut::new();
+        for line in &lines {
+            codec.clone().encode(line, &mut encoded).unwrap();
+        }
+
+        assert_eq!(decode_chunks(codec, &[&encoded]), (lines, false));
+    }
+});
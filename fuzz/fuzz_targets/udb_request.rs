@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protohackers_4_unusual_database_program::split_request;
+
+fuzz_target!(|data: &[u8]| {
+    let (key, value) = split_request(data);
+    assert!(!key.contains(&b'='));
+
+    let mut joined = key.to_vec();
+    if let Some(value) = value {
+        joined.push(b'=');
+        joined.extend_from_slice(value);
+    }
+
+    assert_eq!(joined, data);
+});
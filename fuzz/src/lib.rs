@@ -0,0 +1,44 @@
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+/// Splits `data` in chunks of 1 to 8 bytes, sized after the data itself so the fuzzer explores
+/// chunk boundaries along with the contents.
+pub fn chunks(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+
+    while let Some(&first) = rest.first() {
+        let (chunk, new_rest) = rest.split_at(rest.len().min(1 + (first % 8) as usize));
+        chunks.push(chunk);
+        rest = new_rest;
+    }
+
+    chunks
+}
+
+/// Feeds `chunks` to `decoder` one at a time as if they came from a socket, then decodes up to
+/// EOF. Returns the items decoded before the first error, and whether there was one.
+pub fn decode_chunks<D: Decoder>(mut decoder: D, chunks: &[&[u8]]) -> (Vec<D::Item>, bool) {
+    let mut buf = BytesMut::new();
+    let mut items = Vec::new();
+
+    for chunk in chunks {
+        buf.extend_from_slice(chunk);
+
+        loop {
+            match decoder.decode(&mut buf) {
+                Ok(Some(item)) => items.push(item),
+                Ok(None) => break,
+                Err(_) => return (items, true),
+            }
+        }
+    }
+
+    loop {
+        match decoder.decode_eof(&mut buf) {
+            Ok(Some(item)) => items.push(item),
+            Ok(None) => return (items, false),
+            Err(_) => return (items, true),
+        }
+    }
+}
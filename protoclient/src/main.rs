@@ -0,0 +1,202 @@
+mod mode;
+
+use clap::Parser;
+use futures::StreamExt;
+use mode::{parse_hex, Mode};
+use protohackers_8_insecure_sockets_layer::cipher::ComposedCipher;
+use protohackers_utils::{DelimitedBytesCodec, TransformStream};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{lookup_host, TcpStream, UdpSocket},
+    select,
+    time::timeout,
+};
+use tokio_util::codec::{BytesCodec, Decoder, FramedRead, LinesCodec};
+
+/// How long to keep waiting for datagrams once stdin is closed.
+const UDP_LINGER: Duration = Duration::from_secs(1);
+
+/// Enough for any datagram.
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// Manual testing client for the Protohackers servers.
+///
+/// Every line read from stdin is sent to the server, and everything received is printed to
+/// stdout.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Address of the server
+    #[arg(default_value = "127.0.0.1:1337")]
+    addr: String,
+
+    /// How input lines are sent and received data is shown
+    #[arg(short, long, value_enum, default_value_t = Mode::Lines)]
+    mode: Mode,
+
+    /// Send each input line as a datagram instead of connecting over TCP
+    #[arg(short, long)]
+    udp: bool,
+
+    /// Start with an Insecure Sockets Layer cipher spec, in hex without the final `00`
+    /// (e.g. `02 7b 05`), and cipher the rest of the session with it
+    #[arg(short, long, value_parser = parse_cipher_spec, conflicts_with = "udp")]
+    cipher: Option<CipherSpec>,
+}
+
+#[derive(Debug, Clone)]
+struct CipherSpec {
+    spec: Vec<u8>,
+    cipher: ComposedCipher,
+}
+
+fn parse_cipher_spec(input: &str) -> anyhow::Result<CipherSpec> {
+    let spec = parse_hex(input)?;
+    let cipher =
+        ComposedCipher::from_spec_slice(&spec).ok_or(anyhow::Error::msg("invalid cipher spec"))?;
+
+    if cipher.check_is_noop() {
+        anyhow::bail!("the server rejects no-op ciphers");
+    }
+
+    Ok(CipherSpec { spec, cipher })
+}
+
+async fn send_input<W>(mut write: W, mode: Mode) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = FramedRead::new(io::stdin(), LinesCodec::new());
+
+    while let Some(line) = lines.next().await {
+        match mode.encode_input(&line?, true) {
+            Ok(bytes) => write.write_all(&bytes).await?,
+            Err(err) => eprintln!("Not sent: {err}"),
+        }
+    }
+
+    // Let the server know we're done, it might still have things to say
+    write.shutdown().await?;
+
+    Ok(())
+}
+
+async fn print_frames<R, C>(read: R, codec: C, mode: Mode) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    C: Decoder,
+    C::Item: AsRef<[u8]>,
+    C::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut frames = FramedRead::new(read, codec);
+
+    while let Some(frame) = frames.next().await {
+        println!("{}", mode.format_frame(frame?.as_ref()));
+    }
+
+    Ok(())
+}
+
+async fn run_session<R, W>(read: R, write: W, mode: Mode) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let send = send_input(write, mode);
+    let receive = async {
+        match mode {
+            Mode::Lines | Mode::Json => {
+                print_frames(read, DelimitedBytesCodec::new(b'\n'), mode).await
+            }
+            Mode::Hex => print_frames(read, BytesCodec::new(), mode).await,
+        }
+    };
+
+    tokio::pin!(send, receive);
+
+    let mut is_sending = true;
+
+    // The session is over once the server closes the connection, not when stdin does
+    loop {
+        select! {
+            result = &mut send, if is_sending => {
+                result?;
+                is_sending = false;
+            }
+            result = &mut receive => return result,
+        }
+    }
+}
+
+async fn run_tcp(args: Args) -> anyhow::Result<()> {
+    let stream = TcpStream::connect(&args.addr).await?;
+    let (read, mut write) = stream.into_split();
+
+    let Some(CipherSpec { spec, cipher }) = args.cipher else {
+        return run_session(read, write, args.mode).await;
+    };
+
+    write.write_all(&spec).await?;
+    write.write_all(&[0x00]).await?;
+
+    run_session(
+        TransformStream::new(read, cipher.clone()),
+        TransformStream::new(write, cipher),
+        args.mode,
+    )
+    .await
+}
+
+async fn run_udp(args: Args) -> anyhow::Result<()> {
+    let addr = lookup_host(&args.addr)
+        .await?
+        .next()
+        .ok_or(anyhow::Error::msg("Address didn't resolve"))?;
+
+    let local_addr = match addr {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+
+    let socket = UdpSocket::bind(local_addr).await?;
+    socket.connect(addr).await?;
+
+    let mut lines = FramedRead::new(io::stdin(), LinesCodec::new());
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+
+    loop {
+        select! {
+            line = lines.next() => {
+                let Some(line) = line else { break };
+
+                match args.mode.encode_input(&line?, false) {
+                    Ok(bytes) => {
+                        socket.send(&bytes).await?;
+                    }
+                    Err(err) => eprintln!("Not sent: {err}"),
+                }
+            }
+            read = socket.recv(&mut buf) => {
+                println!("{}", args.mode.format_frame(&buf[..read?]));
+            }
+        }
+    }
+
+    // Answers to the last datagrams may still be on their way
+    while let Ok(read) = timeout(UDP_LINGER, socket.recv(&mut buf)).await {
+        println!("{}", args.mode.format_frame(&buf[..read?]));
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    if args.udp {
+        run_udp(args).await
+    } else {
+        run_tcp(args).await
+    }
+}
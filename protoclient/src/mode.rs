@@ -0,0 +1,92 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use protohackers_utils::HexDump;
+
+/// How lines typed on stdin are sent, and how what's received is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Text lines, newline-delimited over TCP
+    Lines,
+    /// JSON lines, received ones are pretty-printed
+    Json,
+    /// Raw bytes written as hex, received ones are hexdumped
+    Hex,
+}
+
+impl Mode {
+    /// Turns a line typed on stdin into the bytes to send.
+    ///
+    /// Text is newline-terminated when `delimited`, which is what stream protocols expect but not
+    /// datagram ones.
+    pub fn encode_input(self, line: &str, delimited: bool) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Mode::Lines => Ok(delimit(line, delimited)),
+            Mode::Json => {
+                // Still send it, malformed requests are worth testing too
+                if let Err(err) = serde_json::from_str::<serde_json::Value>(line) {
+                    eprintln!("Warning: sending invalid JSON ({err})");
+                }
+
+                Ok(delimit(line, delimited))
+            }
+            Mode::Hex => parse_hex(line),
+        }
+    }
+
+    /// Formats a received frame (or datagram) for display.
+    pub fn format_frame(self, frame: &[u8]) -> String {
+        match self {
+            Mode::Lines => String::from_utf8_lossy(frame).into_owned(),
+            Mode::Json => match serde_json::from_slice::<serde_json::Value>(frame) {
+                Ok(value) => {
+                    serde_json::to_string_pretty(&value).expect("a Value always serializes")
+                }
+                Err(_) => format!("{} (invalid JSON)", String::from_utf8_lossy(frame)),
+            },
+            Mode::Hex => HexDump(frame).to_string(),
+        }
+    }
+}
+
+fn delimit(line: &str, delimited: bool) -> Vec<u8> {
+    let mut bytes = line.as_bytes().to_vec();
+
+    if delimited {
+        bytes.push(b'\n');
+    }
+
+    bytes
+}
+
+/// Parses hex bytes, ignoring whitespace and `:` separators, e.g. `"20 00 0a"` or `"20:00:0a"`.
+pub fn parse_hex(input: &str) -> anyhow::Result<Vec<u8>> {
+    let digits = input
+        .chars()
+        .filter(|char| !char.is_whitespace() && *char != ':')
+        .collect::<Vec<_>>();
+
+    if digits.len() % 2 != 0 {
+        anyhow::bail!("odd number of hex digits");
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = pair.iter().collect::<String>();
+            u8::from_str_radix(&pair, 16).with_context(|| format!("invalid hex byte {pair:?}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("20 00:0a\tFF").unwrap(), [0x20, 0x00, 0x0a, 0xff]);
+        assert!(parse_hex("").unwrap().is_empty());
+        assert!(parse_hex("123").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+}
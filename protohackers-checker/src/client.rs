@@ -0,0 +1,265 @@
+use crate::transcript::Transcript;
+use anyhow::{bail, Context};
+use serde_json::Value;
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream, UdpSocket,
+    },
+    time::timeout,
+};
+
+/// A TCP test client recording its traffic, where every read fails after `timeout`.
+#[derive(Debug)]
+pub struct TcpClient {
+    name: String,
+    read: BufReader<OwnedReadHalf>,
+    write: OwnedWriteHalf,
+    transcript: Transcript,
+    timeout: Duration,
+}
+
+impl TcpClient {
+    pub async fn connect(
+        name: &str,
+        addr: SocketAddr,
+        transcript: Transcript,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .with_context(|| format!("{name} couldn't connect to {addr}"))?;
+
+        transcript.note(name, format!("connected to {addr}"));
+
+        Ok(Self::from_stream(name, stream, transcript, timeout))
+    }
+
+    /// Wraps an already established connection, e.g. an accepted one.
+    pub fn from_stream(
+        name: &str,
+        stream: TcpStream,
+        transcript: Transcript,
+        timeout: Duration,
+    ) -> Self {
+        let (read, write) = stream.into_split();
+
+        Self {
+            name: name.to_owned(),
+            read: BufReader::new(read),
+            write,
+            transcript,
+            timeout,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub async fn send(&mut self, bytes: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let bytes = bytes.as_ref();
+
+        self.transcript.sent(&self.name, bytes);
+        self.write
+            .write_all(bytes)
+            .await
+            .with_context(|| format!("{} couldn't send", self.name))
+    }
+
+    pub async fn send_line(&mut self, line: &str) -> anyhow::Result<()> {
+        self.send(format!("{line}\n")).await
+    }
+
+    pub async fn send_json(&mut self, value: &Value) -> anyhow::Result<()> {
+        self.send_line(&value.to_string()).await
+    }
+
+    /// Closes the writing half, signaling the server that we're done sending.
+    pub async fn shutdown(&mut self) -> anyhow::Result<()> {
+        self.transcript.note(&self.name, "shut down writing");
+        self.write.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Receives a line, without its final `\n`.
+    pub async fn recv_line(&mut self) -> anyhow::Result<String> {
+        let mut line = Vec::new();
+
+        let read = timeout(self.timeout, self.read.read_until(b'\n', &mut line))
+            .await
+            .map_err(|_| self.timed_out("a line"))??;
+
+        if read > 0 {
+            self.transcript.received(&self.name, &line);
+        }
+
+        if line.pop() != Some(b'\n') {
+            bail!("{} got EOF while waiting for a line", self.name);
+        }
+
+        String::from_utf8(line).with_context(|| format!("{} got a non-UTF-8 line", self.name))
+    }
+
+    pub async fn expect_line(&mut self, expected: &str) -> anyhow::Result<()> {
+        let line = self.recv_line().await?;
+
+        if line != expected {
+            bail!("{} expected {expected:?}, got {line:?}", self.name);
+        }
+
+        Ok(())
+    }
+
+    pub async fn recv_json(&mut self) -> anyhow::Result<Value> {
+        let line = self.recv_line().await?;
+
+        serde_json::from_str(&line).with_context(|| format!("{} got invalid JSON", self.name))
+    }
+
+    pub async fn recv_exact(&mut self, len: usize) -> anyhow::Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+
+        timeout(self.timeout, self.read.read_exact(&mut buf))
+            .await
+            .map_err(|_| self.timed_out(&format!("{len} bytes")))?
+            .with_context(|| format!("{} couldn't read {len} bytes", self.name))?;
+
+        self.transcript.received(&self.name, &buf);
+
+        Ok(buf)
+    }
+
+    pub async fn recv_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.recv_exact(1).await?[0])
+    }
+
+    pub async fn expect_bytes(&mut self, expected: &[u8]) -> anyhow::Result<()> {
+        let received = self.recv_exact(expected.len()).await?;
+
+        if received != expected {
+            bail!(
+                "{} expected {expected:02x?}, got {received:02x?}",
+                self.name
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the server to close the connection, ignoring whatever it sends before.
+    pub async fn expect_closed(&mut self) -> anyhow::Result<()> {
+        let mut buf = Vec::new();
+
+        let result = timeout(self.timeout, self.read.read_to_end(&mut buf))
+            .await
+            .map_err(|_| self.timed_out("the connection to be closed"));
+
+        if !buf.is_empty() {
+            self.transcript.received(&self.name, &buf);
+        }
+
+        match result? {
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        self.transcript.note(&self.name, "connection closed");
+
+        Ok(())
+    }
+
+    /// Checks that nothing is received for `duration`.
+    pub async fn expect_silence(&mut self, duration: Duration) -> anyhow::Result<()> {
+        let mut buf = [0; 1024];
+
+        match timeout(duration, self.read.read(&mut buf)).await {
+            Err(_) => Ok(()),
+            Ok(Ok(0)) => bail!("{} got EOF instead of silence", self.name),
+            Ok(Ok(read)) => {
+                self.transcript.received(&self.name, &buf[..read]);
+                bail!("{} expected nothing, got {:?}", self.name, &buf[..read])
+            }
+            Ok(Err(err)) => Err(err.into()),
+        }
+    }
+
+    fn timed_out(&self, what: &str) -> anyhow::Error {
+        anyhow::anyhow!("{} timed out waiting for {what}", self.name)
+    }
+}
+
+/// A UDP test client recording its traffic, where every receive fails after `timeout`.
+#[derive(Debug)]
+pub struct UdpClient {
+    name: String,
+    socket: UdpSocket,
+    transcript: Transcript,
+    timeout: Duration,
+}
+
+impl UdpClient {
+    pub async fn connect(
+        name: &str,
+        addr: SocketAddr,
+        transcript: Transcript,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let local_addr = match addr {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+
+        let socket = UdpSocket::bind(local_addr).await?;
+        socket.connect(addr).await?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            socket,
+            transcript,
+            timeout,
+        })
+    }
+
+    pub async fn send(&self, datagram: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let datagram = datagram.as_ref();
+
+        self.transcript.sent(&self.name, datagram);
+        self.socket.send(datagram).await?;
+
+        Ok(())
+    }
+
+    pub async fn recv(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buf = vec![0; 65536];
+
+        let read = timeout(self.timeout, self.socket.recv(&mut buf))
+            .await
+            .map_err(|_| anyhow::anyhow!("{} timed out waiting for a datagram", self.name))??;
+        buf.truncate(read);
+
+        self.transcript.received(&self.name, &buf);
+
+        Ok(buf)
+    }
+
+    pub async fn expect(&self, expected: impl AsRef<[u8]>) -> anyhow::Result<()> {
+        let expected = expected.as_ref();
+        let received = self.recv().await?;
+
+        if received != expected {
+            bail!(
+                "{} expected {:?}, got {:?}",
+                self.name,
+                String::from_utf8_lossy(expected),
+                String::from_utf8_lossy(&received)
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+mod client;
+mod scenario;
+mod scenarios;
+mod transcript;
+
+use clap::Parser;
+use scenario::CheckerConfig;
+use std::{net::SocketAddr, process::ExitCode, time::Duration};
+
+/// Runs a problem's acceptance scenarios against a local server.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Problem number, from 0 to 9
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=9))]
+    problem: u8,
+
+    /// Address of the server under test
+    #[arg(default_value = "127.0.0.1:1337")]
+    addr: SocketAddr,
+
+    /// Only run the scenarios whose name contains this
+    #[arg(short, long)]
+    scenario: Option<String>,
+
+    /// Show the transcript of passing scenarios too
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Seconds to wait for any expected answer
+    #[arg(long, default_value_t = 5.0)]
+    recv_timeout: f64,
+
+    /// Seconds a whole scenario may take
+    #[arg(long, default_value_t = 60.0)]
+    scenario_timeout: f64,
+
+    /// Address to serve a fake upstream chat server on, for problem 5's proxy to connect to
+    #[arg(long, default_value = "127.0.0.1:16963")]
+    upstream: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let config = CheckerConfig {
+        addr: args.addr,
+        upstream_addr: args.upstream,
+        recv_timeout: Duration::from_secs_f64(args.recv_timeout),
+        scenario_timeout: Duration::from_secs_f64(args.scenario_timeout),
+    };
+
+    let scenarios = scenarios::for_problem(args.problem)
+        .iter()
+        .filter(|scenario| match &args.scenario {
+            Some(filter) => scenario.name.contains(filter.as_str()),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    if scenarios.is_empty() {
+        eprintln!("No scenario to run");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = 0;
+
+    for scenario in &scenarios {
+        println!(
+            "RUN   {}/{}: {}",
+            args.problem, scenario.name, scenario.description
+        );
+
+        let report = scenario.run(&config).await;
+
+        match &report.result {
+            Ok(()) => println!(
+                "PASS  {}/{} ({:.2?})",
+                args.problem, report.name, report.elapsed
+            ),
+            Err(err) => {
+                failed += 1;
+                println!(
+                    "FAIL  {}/{} ({:.2?}): {err:#}",
+                    args.problem, report.name, report.elapsed
+                );
+            }
+        }
+
+        if !report.passed() || args.verbose {
+            print!("{}", report.transcript);
+        }
+    }
+
+    println!("{} passed, {failed} failed", scenarios.len() - failed);
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
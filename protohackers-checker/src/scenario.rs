@@ -0,0 +1,136 @@
+use crate::{
+    client::{TcpClient, UdpClient},
+    transcript::Transcript,
+};
+use futures::future::BoxFuture;
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::Instant;
+
+type ScenarioFn = fn(ScenarioContext) -> BoxFuture<'static, anyhow::Result<()>>;
+
+/// One acceptance check of a problem, run against a live server.
+#[derive(Debug, Clone, Copy)]
+pub struct Scenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    run: ScenarioFn,
+}
+
+impl Scenario {
+    pub const fn new(name: &'static str, description: &'static str, run: ScenarioFn) -> Self {
+        Self {
+            name,
+            description,
+            run,
+        }
+    }
+
+    pub async fn run(&self, config: &CheckerConfig) -> ScenarioReport {
+        let ctx = ScenarioContext {
+            config: config.clone(),
+            transcript: Transcript::new(),
+        };
+        let transcript = ctx.transcript.clone();
+
+        let started_at = Instant::now();
+        let result = match tokio::time::timeout(config.scenario_timeout, (self.run)(ctx)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow::anyhow!(
+                "scenario took longer than {:?}",
+                config.scenario_timeout
+            )),
+        };
+
+        ScenarioReport {
+            name: self.name,
+            result,
+            elapsed: started_at.elapsed(),
+            transcript,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckerConfig {
+    /// Address of the server under test.
+    pub addr: SocketAddr,
+    /// Address the checker serves a fake upstream on, for proxies.
+    pub upstream_addr: SocketAddr,
+    /// How long to wait for any single expected answer.
+    pub recv_timeout: Duration,
+    /// How long a whole scenario may take.
+    pub scenario_timeout: Duration,
+}
+
+/// What a scenario gets to talk to the server.
+#[derive(Debug, Clone)]
+pub struct ScenarioContext {
+    config: CheckerConfig,
+    transcript: Transcript,
+}
+
+impl ScenarioContext {
+    pub async fn tcp(&self, name: &str) -> anyhow::Result<TcpClient> {
+        TcpClient::connect(
+            name,
+            self.config.addr,
+            self.transcript.clone(),
+            self.config.recv_timeout,
+        )
+        .await
+    }
+
+    pub async fn udp(&self, name: &str) -> anyhow::Result<UdpClient> {
+        UdpClient::connect(
+            name,
+            self.config.addr,
+            self.transcript.clone(),
+            self.config.recv_timeout,
+        )
+        .await
+    }
+
+    pub fn upstream_addr(&self) -> SocketAddr {
+        self.config.upstream_addr
+    }
+
+    pub fn recv_timeout(&self) -> Duration {
+        self.config.recv_timeout
+    }
+
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    pub fn note(&self, note: impl Into<String>) {
+        self.transcript.note("checker", note);
+    }
+
+    /// Returns `name` with a suffix unique to this run, so that state left on the server by
+    /// previous runs doesn't get in the way.
+    pub fn unique(&self, name: &str) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        format!("{name}-{nanos:x}")
+    }
+}
+
+#[derive(Debug)]
+pub struct ScenarioReport {
+    pub name: &'static str,
+    pub result: anyhow::Result<()>,
+    pub elapsed: Duration,
+    pub transcript: Transcript,
+}
+
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+use std::time::Duration;
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "chat",
+        "joins, messages and departures are announced to the other users only",
+        |ctx| Box::pin(chat(ctx)),
+    ),
+    Scenario::new(
+        "illegal-name",
+        "users with an illegal name get disconnected",
+        |ctx| Box::pin(illegal_name(ctx)),
+    ),
+];
+
+const SILENCE: Duration = Duration::from_millis(300);
+
+/// Joins as `name`, returning the other users listed in the room.
+async fn join(ctx: &ScenarioContext, name: &str) -> anyhow::Result<(TcpClient, Vec<String>)> {
+    let mut client = ctx.tcp(name).await?;
+
+    // Whatever the welcome message is
+    client.recv_line().await?;
+    client.send_line(name).await?;
+
+    let presence = client.recv_line().await?;
+    let Some(users) = presence.strip_prefix("* ") else {
+        bail!("{name} expected a presence notification starting with '*', got {presence:?}");
+    };
+
+    // The exact wording is free, but the names are at the end, comma-separated
+    let users = users
+        .rsplit_once(':')
+        .map_or("", |(_, users)| users)
+        .split(',')
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+        .map(ToOwned::to_owned)
+        .collect();
+
+    Ok((client, users))
+}
+
+async fn chat(ctx: ScenarioContext) -> anyhow::Result<()> {
+    // The room may already have users from previous runs
+    let alice_name = ctx.unique("alice").replace('-', "");
+    let bob_name = ctx.unique("bob").replace('-', "");
+
+    let (mut alice, _) = join(&ctx, &alice_name).await?;
+    let (mut bob, users) = join(&ctx, &bob_name).await?;
+
+    if !users.contains(&alice_name) {
+        bail!("{bob_name} was told the room contains {users:?}, without {alice_name}");
+    }
+
+    alice
+        .expect_line(&format!("* {bob_name} has entered the room"))
+        .await?;
+
+    bob.send_line("Hi alice!").await?;
+    alice
+        .expect_line(&format!("[{bob_name}] Hi alice!"))
+        .await?;
+    bob.expect_silence(SILENCE).await?;
+
+    alice.send_line("Hello bob :)").await?;
+    bob.expect_line(&format!("[{alice_name}] Hello bob :)"))
+        .await?;
+
+    drop(bob);
+    alice
+        .expect_line(&format!("* {bob_name} has left the room"))
+        .await
+}
+
+async fn illegal_name(ctx: ScenarioContext) -> anyhow::Result<()> {
+    for (i, name) in ["", "bad name", "émilie"].into_iter().enumerate() {
+        let mut client = ctx.tcp(&format!("client{i}")).await?;
+
+        client.recv_line().await?;
+        client.send_line(name).await?;
+        client.expect_closed().await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "ciphered-session",
+        "requests ciphered with the example specs get ciphered answers",
+        |ctx| Box::pin(ciphered_session(ctx)),
+    ),
+    Scenario::new(
+        "noop-cipher",
+        "cipher specs leaving data unchanged get a disconnection",
+        |ctx| Box::pin(noop_cipher(ctx)),
+    ),
+];
+
+/// A request and the line expected in response.
+type Exchange<'a> = (&'a str, &'a str);
+
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    ReverseBits,
+    Xor(u8),
+    XorPos,
+    Add(u8),
+    AddPos,
+}
+
+/// Our own take on the cipher, independent from the server's.
+#[derive(Debug)]
+struct Cipher {
+    operations: Vec<Operation>,
+    send_pos: usize,
+    recv_pos: usize,
+}
+
+impl Cipher {
+    fn new(spec: &[u8]) -> Self {
+        let mut operations = Vec::new();
+        let mut spec = spec.iter();
+
+        while let Some(op) = spec.next() {
+            operations.push(match op {
+                0x01 => Operation::ReverseBits,
+                0x02 => Operation::Xor(*spec.next().unwrap()),
+                0x03 => Operation::XorPos,
+                0x04 => Operation::Add(*spec.next().unwrap()),
+                0x05 => Operation::AddPos,
+                _ => panic!("unknown cipher operation {op:#04x}"),
+            });
+        }
+
+        Self {
+            operations,
+            send_pos: 0,
+            recv_pos: 0,
+        }
+    }
+
+    fn encode(&mut self, bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .map(|&byte| {
+                let pos = self.send_pos as u8;
+                self.send_pos += 1;
+
+                self.operations.iter().fold(byte, |byte, op| match *op {
+                    Operation::ReverseBits => byte.reverse_bits(),
+                    Operation::Xor(n) => byte ^ n,
+                    Operation::XorPos => byte ^ pos,
+                    Operation::Add(n) => byte.wrapping_add(n),
+                    Operation::AddPos => byte.wrapping_add(pos),
+                })
+            })
+            .collect()
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .map(|&byte| {
+                let pos = self.recv_pos as u8;
+                self.recv_pos += 1;
+
+                self.operations
+                    .iter()
+                    .rev()
+                    .fold(byte, |byte, op| match *op {
+                        Operation::ReverseBits => byte.reverse_bits(),
+                        Operation::Xor(n) => byte ^ n,
+                        Operation::XorPos => byte ^ pos,
+                        Operation::Add(n) => byte.wrapping_sub(n),
+                        Operation::AddPos => byte.wrapping_sub(pos),
+                    })
+            })
+            .collect()
+    }
+}
+
+async fn recv_ciphered_line(client: &mut TcpClient, cipher: &mut Cipher) -> anyhow::Result<String> {
+    let mut line = Vec::new();
+
+    while line.last() != Some(&b'\n') {
+        let byte = client.recv_u8().await?;
+        line.extend(cipher.decode(&[byte]));
+    }
+
+    line.pop();
+
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+async fn ciphered_session(ctx: ScenarioContext) -> anyhow::Result<()> {
+    // Cipher spec, then requests and their expected responses
+    let cases: [(&[u8], &[Exchange]); 2] = [
+        (
+            &[0x02, 0x01, 0x01],
+            &[("4x dog,5x car", "5x car"), ("3x rat,2x cat", "3x rat")],
+        ),
+        (
+            &[0x02, 0x7b, 0x05, 0x01],
+            &[
+                (
+                    "10x toy car,15x dog on a string,4x inflatable motorcycle",
+                    "15x dog on a string",
+                ),
+                ("2x toy,100x small cars", "100x small cars"),
+            ],
+        ),
+    ];
+
+    for (i, (spec, requests)) in cases.into_iter().enumerate() {
+        let mut client = ctx.tcp(&format!("client{i}")).await?;
+        let mut cipher = Cipher::new(spec);
+
+        client.send(spec).await?;
+        client.send([0x00]).await?;
+
+        for (request, expected) in requests {
+            ctx.note(format!("sending {request:?} ciphered"));
+            client
+                .send(cipher.encode(format!("{request}\n").as_bytes()))
+                .await?;
+
+            let response = recv_ciphered_line(&mut client, &mut cipher).await?;
+            ctx.note(format!("deciphered {response:?}"));
+
+            if response != *expected {
+                bail!("expected {expected:?}, got {response:?}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn noop_cipher(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let specs: [&[u8]; 4] = [&[], &[0x02, 0x00], &[0x02, 0xab, 0x02, 0xab], &[0x01, 0x01]];
+
+    for (i, spec) in specs.into_iter().enumerate() {
+        let mut client = ctx.tcp(&format!("client{i}")).await?;
+
+        client.send(spec).await?;
+        client.send([0x00]).await?;
+        client.send("4x dog,5x car\n").await?;
+        client.expect_closed().await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+use serde_json::{json, Value};
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "put-get-delete",
+        "jobs are handed out by priority and can be deleted",
+        |ctx| Box::pin(put_get_delete(ctx)),
+    ),
+    Scenario::new(
+        "abort-on-disconnect",
+        "jobs held by a client going away are given to others",
+        |ctx| Box::pin(abort_on_disconnect(ctx)),
+    ),
+    Scenario::new(
+        "wait",
+        "a waiting get is answered once a job is put",
+        |ctx| Box::pin(wait(ctx)),
+    ),
+    Scenario::new(
+        "invalid-request",
+        "invalid requests get an error without closing the connection",
+        |ctx| Box::pin(invalid_request(ctx)),
+    ),
+];
+
+async fn request(client: &mut TcpClient, request: Value) -> anyhow::Result<Value> {
+    client.send_json(&request).await?;
+    client.recv_json().await
+}
+
+async fn expect_status(
+    client: &mut TcpClient,
+    request: Value,
+    status: &str,
+) -> anyhow::Result<Value> {
+    let response = self::request(client, request).await?;
+
+    if response["status"] != status {
+        bail!(
+            "{} expected status {status:?}, got {response}",
+            client.name()
+        );
+    }
+
+    Ok(response)
+}
+
+async fn put(client: &mut TcpClient, queue: &str, job: Value, pri: u64) -> anyhow::Result<u64> {
+    let response = expect_status(
+        client,
+        json!({ "request": "put", "queue": queue, "job": job, "pri": pri }),
+        "ok",
+    )
+    .await?;
+
+    match response["id"].as_u64() {
+        Some(id) => Ok(id),
+        None => bail!("expected a job id, got {response}"),
+    }
+}
+
+async fn expect_job(
+    client: &mut TcpClient,
+    queues: &[&str],
+    id: u64,
+    pri: u64,
+) -> anyhow::Result<()> {
+    let response =
+        expect_status(client, json!({ "request": "get", "queues": queues }), "ok").await?;
+
+    if response["id"] != id || response["pri"] != pri {
+        bail!(
+            "{} expected job {id} with priority {pri}, got {response}",
+            client.name()
+        );
+    }
+
+    Ok(())
+}
+
+async fn put_get_delete(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let queue1 = ctx.unique("queue1");
+    let queue2 = ctx.unique("queue2");
+    let mut client = ctx.tcp("client").await?;
+
+    let low = put(&mut client, &queue1, json!({ "title": "low" }), 10).await?;
+    let high = put(&mut client, &queue2, json!({ "title": "high" }), 100).await?;
+
+    expect_job(&mut client, &[&queue1, &queue2], high, 100).await?;
+
+    expect_status(&mut client, json!({ "request": "delete", "id": low }), "ok").await?;
+    expect_status(
+        &mut client,
+        json!({ "request": "delete", "id": low }),
+        "no-job",
+    )
+    .await?;
+    expect_status(
+        &mut client,
+        json!({ "request": "get", "queues": [queue1] }),
+        "no-job",
+    )
+    .await?;
+
+    // Deleting a job someone is working on works too
+    expect_status(
+        &mut client,
+        json!({ "request": "delete", "id": high }),
+        "ok",
+    )
+    .await?;
+    expect_status(
+        &mut client,
+        json!({ "request": "abort", "id": high }),
+        "no-job",
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn abort_on_disconnect(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let queue = ctx.unique("queue");
+    let mut alice = ctx.tcp("alice").await?;
+    let mut bob = ctx.tcp("bob").await?;
+
+    let id = put(&mut alice, &queue, json!("job"), 1).await?;
+    expect_job(&mut alice, &[&queue], id, 1).await?;
+
+    // Nobody but the client working on a job can abort it
+    let response = request(&mut bob, json!({ "request": "abort", "id": id })).await?;
+    if response["status"] == "ok" {
+        bail!("bob could abort a job alice is working on");
+    }
+
+    drop(alice);
+    ctx.note("alice disconnected while working on the job");
+
+    expect_status(
+        &mut bob,
+        json!({ "request": "get", "queues": [queue], "wait": true }),
+        "ok",
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn wait(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let queue = ctx.unique("queue");
+    let mut alice = ctx.tcp("alice").await?;
+    let mut bob = ctx.tcp("bob").await?;
+
+    alice
+        .send_json(&json!({ "request": "get", "queues": [queue], "wait": true }))
+        .await?;
+
+    let id = put(&mut bob, &queue, json!({}), 5).await?;
+
+    let response = alice.recv_json().await?;
+    if response["status"] != "ok" || response["id"] != id {
+        bail!("expected job {id}, got {response}");
+    }
+
+    Ok(())
+}
+
+async fn invalid_request(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let queue = ctx.unique("queue");
+    let mut client = ctx.tcp("client").await?;
+
+    let invalid = [
+        json!({ "request": "nope" }),
+        json!({ "request": "put", "queue": queue }),
+        json!({ "request": "get" }),
+        json!({ "request": "delete", "id": "1" }),
+        json!([]),
+    ];
+
+    for request in invalid {
+        expect_status(&mut client, request, "error").await?;
+    }
+
+    client.send_line("not json").await?;
+    let response = client.recv_json().await?;
+    if response["status"] != "error" {
+        bail!("expected an error, got {response}");
+    }
+
+    put(&mut client, &queue, json!({ "still": "open" }), 0).await?;
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use crate::{
+    client::UdpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "session",
+        "lines sent over a session, some with escaped characters, come back reversed",
+        |ctx| Box::pin(session(ctx)),
+    ),
+    Scenario::new(
+        "lossy",
+        "duplicated, out of order and unacknowledged packets are handled like on a lossy network",
+        |ctx| Box::pin(lossy(ctx)),
+    ),
+    Scenario::new(
+        "unknown-session",
+        "packets for sessions that don't exist get closed",
+        |ctx| Box::pin(unknown_session(ctx)),
+    ),
+];
+
+fn session_id(ctx: &ScenarioContext) -> u32 {
+    let unique = ctx.unique("");
+    u32::from_str_radix(&unique[unique.len() - 7..], 16).unwrap()
+}
+
+/// Receives packets until all of `expected` are seen, in any order, ignoring the others (e.g.
+/// retransmissions).
+async fn expect_packets(client: &UdpClient, expected: &[String]) -> anyhow::Result<()> {
+    let mut missing = expected.to_vec();
+
+    while !missing.is_empty() {
+        let packet = client.recv().await?;
+        let packet = String::from_utf8_lossy(&packet);
+
+        missing.retain(|expected| *expected != packet);
+    }
+
+    Ok(())
+}
+
+async fn connect(client: &UdpClient, session: u32) -> anyhow::Result<()> {
+    client.send(format!("/connect/{session}/")).await?;
+    expect_packets(client, &[format!("/ack/{session}/0/")]).await
+}
+
+async fn session(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let client = ctx.udp("client").await?;
+    let session = session_id(&ctx);
+
+    connect(&client, session).await?;
+
+    client.send(format!("/data/{session}/0/hel/")).await?;
+    expect_packets(&client, &[format!("/ack/{session}/3/")]).await?;
+
+    client.send(format!("/data/{session}/3/lo\n/")).await?;
+    expect_packets(
+        &client,
+        &[
+            format!("/ack/{session}/6/"),
+            format!("/data/{session}/0/olleh\n/"),
+        ],
+    )
+    .await?;
+    client.send(format!("/ack/{session}/6/")).await?;
+
+    // "a/b\c\n" escaped, reversed into "c\b/a\n"
+    client
+        .send(format!("/data/{session}/6/a\\/b\\\\c\n/"))
+        .await?;
+    expect_packets(
+        &client,
+        &[
+            format!("/ack/{session}/12/"),
+            format!("/data/{session}/6/c\\\\b\\/a\n/"),
+        ],
+    )
+    .await?;
+    client.send(format!("/ack/{session}/12/")).await?;
+
+    client.send(format!("/close/{session}/")).await?;
+    expect_packets(&client, &[format!("/close/{session}/")]).await
+}
+
+async fn lossy(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let client = ctx.udp("client").await?;
+    let session = session_id(&ctx);
+
+    // Our first ack got lost, so we connect again
+    connect(&client, session).await?;
+    connect(&client, session).await?;
+
+    client.send(format!("/data/{session}/0/hel/")).await?;
+    expect_packets(&client, &[format!("/ack/{session}/3/")]).await?;
+
+    ctx.note("skipping position 3, as if the packet got lost");
+    client.send(format!("/data/{session}/5/xyz/")).await?;
+    expect_packets(&client, &[format!("/ack/{session}/3/")]).await?;
+
+    ctx.note("resending a packet, as if its ack got lost");
+    client.send(format!("/data/{session}/0/hel/")).await?;
+    expect_packets(&client, &[format!("/ack/{session}/3/")]).await?;
+
+    client.send(format!("/data/{session}/3/lo\n/")).await?;
+    expect_packets(
+        &client,
+        &[
+            format!("/ack/{session}/6/"),
+            format!("/data/{session}/0/olleh\n/"),
+        ],
+    )
+    .await?;
+
+    ctx.note("not acking, the data must be retransmitted");
+    expect_packets(&client, &[format!("/data/{session}/0/olleh\n/")]).await?;
+    client.send(format!("/ack/{session}/6/")).await?;
+
+    client.send(format!("/close/{session}/")).await?;
+    expect_packets(&client, &[format!("/close/{session}/")]).await
+}
+
+async fn unknown_session(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let client = ctx.udp("client").await?;
+    let session = session_id(&ctx);
+
+    client.send(format!("/data/{session}/0/hello\n/")).await?;
+    expect_packets(&client, &[format!("/close/{session}/")]).await?;
+
+    client.send(format!("/ack/{session}/0/")).await?;
+    expect_packets(&client, &[format!("/close/{session}/")]).await
+}
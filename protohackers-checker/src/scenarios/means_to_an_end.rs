@@ -0,0 +1,97 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "example",
+        "the example session from the problem statement, split across writes",
+        |ctx| Box::pin(example(ctx)),
+    ),
+    Scenario::new(
+        "empty-ranges",
+        "queries matching nothing, or with min > max, return 0",
+        |ctx| Box::pin(empty_ranges(ctx)),
+    ),
+    Scenario::new(
+        "isolated-sessions",
+        "prices inserted by a client aren't seen by another",
+        |ctx| Box::pin(isolated_sessions(ctx)),
+    ),
+];
+
+fn message(kind: u8, first: i32, second: i32) -> [u8; 9] {
+    let mut message = [0; 9];
+    message[0] = kind;
+    message[1..5].copy_from_slice(&first.to_be_bytes());
+    message[5..9].copy_from_slice(&second.to_be_bytes());
+    message
+}
+
+fn insert(timestamp: i32, price: i32) -> [u8; 9] {
+    message(b'I', timestamp, price)
+}
+
+fn query(mintime: i32, maxtime: i32) -> [u8; 9] {
+    message(b'Q', mintime, maxtime)
+}
+
+async fn expect_mean(client: &mut TcpClient, expected: i32) -> anyhow::Result<()> {
+    let mean = i32::from_be_bytes(client.recv_exact(4).await?.try_into().unwrap());
+
+    if mean != expected {
+        bail!(
+            "{} expected a mean of {expected}, got {mean}",
+            client.name()
+        );
+    }
+
+    Ok(())
+}
+
+async fn example(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let mut client = ctx.tcp("client").await?;
+
+    let first = insert(12345, 101);
+    client.send(&first[..3]).await?;
+    client.send(&first[3..]).await?;
+
+    let mut rest = Vec::new();
+    rest.extend(insert(12346, 102));
+    rest.extend(insert(12347, 100));
+    rest.extend(insert(40960, 5));
+    client.send(rest).await?;
+
+    client.send(query(12288, 16384)).await?;
+    expect_mean(&mut client, 101).await
+}
+
+async fn empty_ranges(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let mut client = ctx.tcp("client").await?;
+
+    client.send(query(0, 1000)).await?;
+    expect_mean(&mut client, 0).await?;
+
+    client.send(insert(100, 10)).await?;
+    client.send(query(200, 100)).await?;
+    expect_mean(&mut client, 0).await?;
+
+    client.send(query(i32::MIN, i32::MAX)).await?;
+    expect_mean(&mut client, 10).await
+}
+
+async fn isolated_sessions(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let mut alice = ctx.tcp("alice").await?;
+    let mut bob = ctx.tcp("bob").await?;
+
+    alice.send(insert(1, 1000)).await?;
+    bob.send(insert(2, 10)).await?;
+
+    alice.send(query(0, 10)).await?;
+    expect_mean(&mut alice, 1000).await?;
+
+    bob.send(query(0, 10)).await?;
+    expect_mean(&mut bob, 10).await
+}
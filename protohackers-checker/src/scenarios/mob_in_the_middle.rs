@@ -0,0 +1,100 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::Context;
+use tokio::{net::TcpListener, time::timeout};
+
+pub const SCENARIOS: &[Scenario] = &[Scenario::new(
+    "rewrite-boguscoin",
+    "Boguscoin addresses are rewritten both ways, and nothing else is \
+     (start the proxy with MOB_UPSTREAM_ADDR set to --upstream)",
+    |ctx| Box::pin(rewrite_boguscoin(ctx)),
+)];
+
+const TONY: &str = "7YWHMfk9JZe0LM0g1ZauHuiSxhI";
+
+/// Accepts the proxy's connection on the fake upstream server.
+async fn accept_upstream(
+    ctx: &ScenarioContext,
+    listener: &TcpListener,
+) -> anyhow::Result<TcpClient> {
+    let (stream, _) = timeout(ctx.recv_timeout(), listener.accept())
+        .await
+        .context("the proxy didn't connect to the upstream server")??;
+
+    ctx.note("the proxy connected upstream");
+
+    Ok(TcpClient::from_stream(
+        "upstream",
+        stream,
+        ctx.transcript().clone(),
+        ctx.recv_timeout(),
+    ))
+}
+
+async fn rewrite_boguscoin(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(ctx.upstream_addr())
+        .await
+        .with_context(|| {
+            format!(
+                "couldn't serve the fake upstream on {}",
+                ctx.upstream_addr()
+            )
+        })?;
+
+    let mut alice = ctx.tcp("alice").await?;
+    let mut upstream = accept_upstream(&ctx, &listener).await?;
+
+    upstream
+        .send_line("Welcome to budgetchat! What shall I call you?")
+        .await?;
+    alice
+        .expect_line("Welcome to budgetchat! What shall I call you?")
+        .await?;
+
+    alice.send_line("alice").await?;
+    upstream.expect_line("alice").await?;
+
+    // Client to server
+    let cases = [
+        (
+            "Hi, send to 7F1u3wSD5RbOHQmupo9nx4TnhQ please".to_owned(),
+            format!("Hi, send to {TONY} please"),
+        ),
+        (
+            "7F1u3wSD5RbOHQmupo9nx4TnhQ 7LOrwbDlS8NujgjddyogWgIM93MV5N2VR".to_owned(),
+            format!("{TONY} {TONY}"),
+        ),
+    ];
+
+    for (sent, expected) in &cases {
+        alice.send_line(sent).await?;
+        upstream.expect_line(expected).await?;
+    }
+
+    // Server to client, including things that only look like addresses
+    let cases = [
+        (
+            "[bob] My address is 7iKDZEwPZSqIvDnHvVN2r0hUWXD5rHX".to_owned(),
+            format!("[bob] My address is {TONY}"),
+        ),
+        (
+            "[bob] Not addresses: 7abc x7F1u3wSD5RbOHQmupo9nx4TnhQ \
+             7F1u3wSD5RbOHQmupo9nx4TnhQ7F1u3wSD5RbOHQmupo9nx4TnhQ"
+                .to_owned(),
+            "[bob] Not addresses: 7abc x7F1u3wSD5RbOHQmupo9nx4TnhQ \
+             7F1u3wSD5RbOHQmupo9nx4TnhQ7F1u3wSD5RbOHQmupo9nx4TnhQ"
+                .to_owned(),
+        ),
+    ];
+
+    for (sent, expected) in &cases {
+        upstream.send_line(sent).await?;
+        alice.expect_line(expected).await?;
+    }
+
+    // Leaving must end the upstream session too
+    drop(alice);
+    upstream.expect_closed().await
+}
@@ -0,0 +1,28 @@
+mod budget_chat;
+mod insecure_sockets_layer;
+mod job_centre;
+mod line_reversal;
+mod means_to_an_end;
+mod mob_in_the_middle;
+mod prime_time;
+mod smoke_test;
+mod speed_daemon;
+mod unusual_database_program;
+
+use crate::scenario::Scenario;
+
+pub fn for_problem(problem: u8) -> &'static [Scenario] {
+    match problem {
+        0 => smoke_test::SCENARIOS,
+        1 => prime_time::SCENARIOS,
+        2 => means_to_an_end::SCENARIOS,
+        3 => budget_chat::SCENARIOS,
+        4 => unusual_database_program::SCENARIOS,
+        5 => mob_in_the_middle::SCENARIOS,
+        6 => speed_daemon::SCENARIOS,
+        7 => line_reversal::SCENARIOS,
+        8 => insecure_sockets_layer::SCENARIOS,
+        9 => job_centre::SCENARIOS,
+        _ => &[],
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+use serde_json::{json, Value};
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "well-formed",
+        "pipelined well-formed requests are all answered correctly and in order",
+        |ctx| Box::pin(well_formed(ctx)),
+    ),
+    Scenario::new(
+        "malformed",
+        "malformed requests get a malformed response and a disconnection",
+        |ctx| Box::pin(malformed(ctx)),
+    ),
+];
+
+fn is_prime_request(number: Value) -> Value {
+    json!({ "method": "isPrime", "number": number })
+}
+
+/// Returns the `prime` field of a well-formed response.
+fn parse_response(response: &Value) -> Option<bool> {
+    if response.get("method")? != "isPrime" {
+        return None;
+    }
+
+    response.get("prime")?.as_bool()
+}
+
+async fn well_formed(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let cases = [
+        (json!(2), true),
+        (json!(7), true),
+        (json!(4), false),
+        (json!(1), false),
+        (json!(0), false),
+        (json!(-7), false),
+        (json!(3.5), false),
+        (json!(1_000_003), true),
+        (json!(123_456_789_012_u64), false),
+    ];
+
+    let mut client = ctx.tcp("client").await?;
+
+    // Send everything first, the answers must still come back in order
+    for (number, _) in &cases {
+        let mut request = is_prime_request(number.clone());
+        // Unknown fields must be ignored
+        request["extra"] = json!("ignored");
+
+        client.send_json(&request).await?;
+    }
+
+    for (number, expected) in cases {
+        let response = client.recv_json().await?;
+
+        match parse_response(&response) {
+            Some(prime) if prime == expected => {}
+            Some(prime) => bail!("got prime={prime} for {number}, expected {expected}"),
+            None => bail!("got a malformed response for {number}: {response}"),
+        }
+    }
+
+    Ok(())
+}
+
+async fn expect_malformed_response(client: &mut TcpClient) -> anyhow::Result<()> {
+    let line = client.recv_line().await?;
+
+    if let Ok(response) = serde_json::from_str::<Value>(&line) {
+        if parse_response(&response).is_some() {
+            bail!("got a well-formed response to a malformed request");
+        }
+    }
+
+    client.expect_closed().await
+}
+
+async fn malformed(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let requests = [
+        "not json".to_owned(),
+        json!({ "method": "isPrime" }).to_string(),
+        json!({ "method": "isPrime", "number": "7" }).to_string(),
+        json!({ "method": "isNotPrime", "number": 7 }).to_string(),
+        json!([]).to_string(),
+    ];
+
+    for (i, request) in requests.iter().enumerate() {
+        let mut client = ctx.tcp(&format!("client{i}")).await?;
+
+        // A valid request first, to check the connection is only dropped for the bad one
+        client.send_json(&is_prime_request(json!(13))).await?;
+        client.send_line(request).await?;
+
+        let response = client.recv_json().await?;
+        if parse_response(&response) != Some(true) {
+            bail!("got a wrong response to the request before the malformed one: {response}");
+        }
+
+        expect_malformed_response(&mut client).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use crate::scenario::{Scenario, ScenarioContext};
+use anyhow::bail;
+use futures::future;
+
+pub const SCENARIOS: &[Scenario] = &[Scenario::new(
+    "echo",
+    "several clients at once get back exactly what they sent, then get disconnected",
+    |ctx| Box::pin(echo(ctx)),
+)];
+
+const CLIENTS: usize = 5;
+const CHUNKS: usize = 8;
+const CHUNK_SIZE: usize = 1000;
+
+async fn echo_client(ctx: &ScenarioContext, i: usize) -> anyhow::Result<()> {
+    let mut client = ctx.tcp(&format!("client{i}")).await?;
+
+    // Every byte value, binary data must go through untouched
+    let data = (0..CHUNKS * CHUNK_SIZE)
+        .map(|n| (n * 7 + i) as u8)
+        .collect::<Vec<_>>();
+
+    for chunk in data.chunks(CHUNK_SIZE) {
+        client.send(chunk).await?;
+    }
+
+    client.shutdown().await?;
+
+    let echoed = client.recv_exact(data.len()).await?;
+
+    if echoed != data {
+        bail!("{} got back different data", client.name());
+    }
+
+    client.expect_closed().await
+}
+
+async fn echo(ctx: ScenarioContext) -> anyhow::Result<()> {
+    future::try_join_all((0..CLIENTS).map(|i| echo_client(&ctx, i))).await?;
+
+    Ok(())
+}
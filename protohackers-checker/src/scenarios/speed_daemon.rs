@@ -0,0 +1,165 @@
+use crate::{
+    client::TcpClient,
+    scenario::{Scenario, ScenarioContext},
+};
+use anyhow::bail;
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "multi-camera-ticket",
+        "a car seen speeding by two cameras gets a ticket sent to the road's dispatcher",
+        |ctx| Box::pin(multi_camera_ticket(ctx)),
+    ),
+    Scenario::new(
+        "heartbeat",
+        "heartbeats are sent at the requested interval",
+        |ctx| Box::pin(heartbeat(ctx)),
+    ),
+    Scenario::new(
+        "illegal-messages",
+        "unknown or misplaced messages get an error and a disconnection",
+        |ctx| Box::pin(illegal_messages(ctx)),
+    ),
+];
+
+const ERROR: u8 = 0x10;
+const TICKET: u8 = 0x21;
+const HEARTBEAT: u8 = 0x41;
+
+fn put_str(message: &mut Vec<u8>, str: &str) {
+    message.push(str.len() as u8);
+    message.extend(str.as_bytes());
+}
+
+fn plate(plate: &str, timestamp: u32) -> Vec<u8> {
+    let mut message = vec![0x20];
+    put_str(&mut message, plate);
+    message.extend(timestamp.to_be_bytes());
+    message
+}
+
+fn want_heartbeat(deciseconds: u32) -> Vec<u8> {
+    let mut message = vec![0x40];
+    message.extend(deciseconds.to_be_bytes());
+    message
+}
+
+fn i_am_camera(road: u16, mile: u16, limit: u16) -> Vec<u8> {
+    let mut message = vec![0x80];
+    message.extend(road.to_be_bytes());
+    message.extend(mile.to_be_bytes());
+    message.extend(limit.to_be_bytes());
+    message
+}
+
+fn i_am_dispatcher(roads: &[u16]) -> Vec<u8> {
+    let mut message = vec![0x81, roads.len() as u8];
+    message.extend(roads.iter().flat_map(|road| road.to_be_bytes()));
+    message
+}
+
+async fn recv_str(client: &mut TcpClient) -> anyhow::Result<String> {
+    let len = client.recv_u8().await?;
+    let bytes = client.recv_exact(len.into()).await?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+async fn recv_u16(client: &mut TcpClient) -> anyhow::Result<u16> {
+    Ok(u16::from_be_bytes(
+        client.recv_exact(2).await?.try_into().unwrap(),
+    ))
+}
+
+async fn recv_u32(client: &mut TcpClient) -> anyhow::Result<u32> {
+    Ok(u32::from_be_bytes(
+        client.recv_exact(4).await?.try_into().unwrap(),
+    ))
+}
+
+async fn expect_error(client: &mut TcpClient) -> anyhow::Result<()> {
+    let kind = client.recv_u8().await?;
+
+    if kind != ERROR {
+        bail!(
+            "{} expected an error message, got type {kind:#04x}",
+            client.name()
+        );
+    }
+
+    recv_str(client).await?;
+    client.expect_closed().await
+}
+
+async fn multi_camera_ticket(ctx: ScenarioContext) -> anyhow::Result<()> {
+    // Random-ish road and plate, tickets from previous runs could be pending otherwise
+    let unique = ctx.unique("");
+    let road = u16::from_str_radix(&unique[unique.len() - 4..], 16)?;
+    let car = format!("UN{}", &unique[unique.len() - 6..]).to_uppercase();
+
+    let mut camera1 = ctx.tcp("camera1").await?;
+    let mut camera2 = ctx.tcp("camera2").await?;
+    let mut dispatcher = ctx.tcp("dispatcher").await?;
+
+    camera1.send(i_am_camera(road, 8, 60)).await?;
+    camera1.send(plate(&car, 0)).await?;
+
+    camera2.send(i_am_camera(road, 9, 60)).await?;
+    camera2.send(plate(&car, 45)).await?;
+
+    dispatcher.send(i_am_dispatcher(&[road])).await?;
+
+    let kind = dispatcher.recv_u8().await?;
+    if kind != TICKET {
+        bail!("expected a ticket, got message type {kind:#04x}");
+    }
+
+    let ticket = (
+        recv_str(&mut dispatcher).await?,
+        recv_u16(&mut dispatcher).await?,
+        recv_u16(&mut dispatcher).await?,
+        recv_u32(&mut dispatcher).await?,
+        recv_u16(&mut dispatcher).await?,
+        recv_u32(&mut dispatcher).await?,
+        recv_u16(&mut dispatcher).await?,
+    );
+    let expected = (car, road, 8, 0, 9, 45, 8000);
+
+    if ticket != expected {
+        bail!("expected ticket {expected:?}, got {ticket:?}");
+    }
+
+    Ok(())
+}
+
+async fn heartbeat(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let mut client = ctx.tcp("client").await?;
+
+    client.send(want_heartbeat(2)).await?;
+
+    for _ in 0..5 {
+        client.expect_bytes(&[HEARTBEAT]).await?;
+    }
+
+    Ok(())
+}
+
+async fn illegal_messages(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let mut unknown = ctx.tcp("unknown").await?;
+    unknown.send([0xff]).await?;
+    expect_error(&mut unknown).await?;
+
+    let mut not_a_camera = ctx.tcp("not-a-camera").await?;
+    not_a_camera.send(plate("UN1X", 0)).await?;
+    expect_error(&mut not_a_camera).await?;
+
+    let mut camera_twice = ctx.tcp("camera-twice").await?;
+    camera_twice.send(i_am_camera(1, 1, 60)).await?;
+    camera_twice.send(i_am_camera(1, 2, 60)).await?;
+    expect_error(&mut camera_twice).await?;
+
+    let mut heartbeat_twice = ctx.tcp("heartbeat-twice").await?;
+    heartbeat_twice.send(want_heartbeat(0)).await?;
+    heartbeat_twice.send(want_heartbeat(0)).await?;
+    expect_error(&mut heartbeat_twice).await
+}
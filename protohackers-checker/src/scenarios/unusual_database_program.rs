@@ -0,0 +1,56 @@
+use crate::scenario::{Scenario, ScenarioContext};
+use anyhow::bail;
+
+pub const SCENARIOS: &[Scenario] = &[
+    Scenario::new(
+        "insert-retrieve",
+        "values are stored and retrieved, splitting on the first '=' only",
+        |ctx| Box::pin(insert_retrieve(ctx)),
+    ),
+    Scenario::new(
+        "version",
+        "the version key is answered and can't be modified",
+        |ctx| Box::pin(version(ctx)),
+    ),
+];
+
+async fn insert_retrieve(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let client = ctx.udp("client").await?;
+    let key = ctx.unique("key");
+
+    let cases = [
+        (format!("{key}=bar"), format!("{key}=bar")),
+        (format!("{key}=bar=baz"), format!("{key}=bar=baz")),
+        (format!("{key}="), format!("{key}=")),
+        (format!("{key}==="), format!("{key}===")),
+    ];
+
+    for (insert, expected) in cases {
+        client.send(&insert).await?;
+        client.send(&key).await?;
+        client.expect(expected).await?;
+    }
+
+    let empty_key_value = ctx.unique("=value with an empty key");
+    client.send(&empty_key_value).await?;
+    client.send("").await?;
+    client.expect(empty_key_value).await
+}
+
+async fn version(ctx: ScenarioContext) -> anyhow::Result<()> {
+    let client = ctx.udp("client").await?;
+
+    client.send("version").await?;
+    let version = client.recv().await?;
+
+    if !version.starts_with(b"version=") {
+        bail!(
+            "expected a version, got {:?}",
+            String::from_utf8_lossy(&version)
+        );
+    }
+
+    client.send("version=hacked").await?;
+    client.send("version").await?;
+    client.expect(version).await
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt::{self, Display},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+#[derive(Debug)]
+enum EntryKind {
+    Sent(Vec<u8>),
+    Received(Vec<u8>),
+    Note(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    at: Duration,
+    peer: String,
+    kind: EntryKind,
+}
+
+#[derive(Debug)]
+struct Inner {
+    started_at: Instant,
+    entries: Vec<Entry>,
+}
+
+/// Everything that went through the wire during a scenario, shown when it fails.
+///
+/// Cheap to clone, all the clones record into the same transcript.
+#[derive(Debug, Clone)]
+pub struct Transcript(Arc<Mutex<Inner>>);
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            started_at: Instant::now(),
+            entries: Vec::new(),
+        })))
+    }
+
+    pub fn sent(&self, peer: &str, bytes: &[u8]) {
+        self.push(peer, EntryKind::Sent(bytes.to_vec()));
+    }
+
+    pub fn received(&self, peer: &str, bytes: &[u8]) {
+        self.push(peer, EntryKind::Received(bytes.to_vec()));
+    }
+
+    pub fn note(&self, peer: &str, note: impl Into<String>) {
+        self.push(peer, EntryKind::Note(note.into()));
+    }
+
+    fn push(&self, peer: &str, kind: EntryKind) {
+        let mut inner = self.0.lock().unwrap();
+        let at = inner.started_at.elapsed();
+
+        inner.entries.push(Entry {
+            at,
+            peer: peer.to_owned(),
+            kind,
+        });
+    }
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.0.lock().unwrap();
+
+        for Entry { at, peer, kind } in &inner.entries {
+            write!(f, "[{:>8.3}s] {peer} ", at.as_secs_f64())?;
+
+            match kind {
+                EntryKind::Sent(bytes) => writeln!(f, "--> {}", DisplayBytes(bytes))?,
+                EntryKind::Received(bytes) => writeln!(f, "<-- {}", DisplayBytes(bytes))?,
+                EntryKind::Note(note) => writeln!(f, "*** {note}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Text as a quoted string, anything else as hex bytes, both cut after [`Self::MAX_LEN`] bytes.
+struct DisplayBytes<'a>(&'a [u8]);
+
+impl DisplayBytes<'_> {
+    const MAX_LEN: usize = 128;
+}
+
+impl Display for DisplayBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown = &self.0[..self.0.len().min(Self::MAX_LEN)];
+
+        match std::str::from_utf8(shown) {
+            Ok(text) if !text.chars().any(|char| char.is_control() && char != '\n') => {
+                write!(f, "{text:?}")?;
+            }
+            _ => {
+                for (i, byte) in shown.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+
+                    write!(f, "{byte:02x}")?;
+                }
+            }
+        }
+
+        if shown.len() < self.0.len() {
+            write!(f, " ... ({} bytes)", self.0.len())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{cmp, io};
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// A binary-safe [`Decoder`] and [`Encoder`] that splits data on a delimiter byte.
+///
+/// Frames are returned as [`Bytes`] split off the read buffer, so no copying is involved. The
+/// delimiter is not included in the returned frames.
+///
+/// By default a trailing partial frame (one missing its delimiter right before EOF) is yielded as
+/// a last frame, like [`tokio_util::codec::LinesCodec`] does. In strict EOF mode it is silently
+/// discarded instead, which is what [`StrictLinesCodec`](crate::StrictLinesCodec) builds upon.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DelimitedBytesCodec {
+    /// Byte that ends every frame.
+    delimiter: u8,
+
+    // Stored index of the next index to examine for the delimiter, so we don't search again the
+    // bytes we already looked at in a previous `decode` call.
+    next_index: usize,
+
+    /// The maximum length for a given frame. If `usize::MAX`, frames will be read until the
+    /// delimiter is reached.
+    max_length: usize,
+
+    /// Are we currently discarding the remainder of a frame which was over the length limit?
+    is_discarding: bool,
+
+    /// Should a trailing frame without delimiter be discarded at EOF?
+    strict_eof: bool,
+}
+
+impl DelimitedBytesCodec {
+    /// Returns a `DelimitedBytesCodec` splitting frames on `delimiter`, without any limit on the
+    /// frame length.
+    ///
+    /// See [`StrictLinesCodec::new_with_max_length`](crate::StrictLinesCodec::new_with_max_length)
+    /// for why this could be a potential security risk.
+    pub fn new(delimiter: u8) -> Self {
+        Self {
+            delimiter,
+            next_index: 0,
+            max_length: usize::MAX,
+            is_discarding: false,
+            strict_eof: false,
+        }
+    }
+
+    /// Returns a `DelimitedBytesCodec` with a maximum frame length limit.
+    ///
+    /// Frames over the limit return [`DelimitedBytesCodecError::MaxLengthExceeded`] and are then
+    /// discarded up to the next delimiter.
+    pub fn new_with_max_length(delimiter: u8, max_length: usize) -> Self {
+        Self {
+            max_length,
+            ..Self::new(delimiter)
+        }
+    }
+
+    /// Sets whether a trailing frame without delimiter should be discarded at EOF.
+    pub fn with_strict_eof(self, strict_eof: bool) -> Self {
+        Self { strict_eof, ..self }
+    }
+
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    pub fn is_strict_eof(&self) -> bool {
+        self.strict_eof
+    }
+}
+
+impl Decoder for DelimitedBytesCodec {
+    type Item = Bytes;
+    type Error = DelimitedBytesCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+        loop {
+            // Determine how far into the buffer we'll search for a delimiter. If there's no
+            // max_length set, we'll read to the end of the buffer.
+            let read_to = cmp::min(self.max_length.saturating_add(1), buf.len());
+
+            let delimiter_offset = buf[self.next_index..read_to]
+                .iter()
+                .position(|b| *b == self.delimiter);
+
+            match (self.is_discarding, delimiter_offset) {
+                (true, Some(offset)) => {
+                    // Discard up to the delimiter and then stop discarding. On the next
+                    // iteration, we'll try to read a frame normally.
+                    buf.advance(offset + self.next_index + 1);
+                    self.is_discarding = false;
+                    self.next_index = 0;
+                }
+                (true, None) => {
+                    // Discard everything we read. On the next iteration, we'll continue
+                    // discarding up to max_len bytes unless we find a delimiter.
+                    buf.advance(read_to);
+                    self.next_index = 0;
+                    if buf.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                (false, Some(offset)) => {
+                    let delimiter_index = offset + self.next_index;
+                    self.next_index = 0;
+                    let mut frame = buf.split_to(delimiter_index + 1);
+                    frame.truncate(delimiter_index);
+                    return Ok(Some(frame.freeze()));
+                }
+                (false, None) if buf.len() > self.max_length => {
+                    // Reached the maximum length without finding a delimiter, return an error
+                    // and start discarding on the next call.
+                    self.is_discarding = true;
+                    return Err(DelimitedBytesCodecError::MaxLengthExceeded);
+                }
+                (false, None) => {
+                    // We didn't find a frame or reach the length limit, so the next call will
+                    // resume searching at the current offset.
+                    self.next_index = read_to;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+        if let Some(frame) = self.decode(buf)? {
+            return Ok(Some(frame));
+        }
+
+        self.next_index = 0;
+
+        if buf.is_empty() || self.is_discarding || self.strict_eof {
+            self.is_discarding = false;
+            buf.clear();
+            return Ok(None);
+        }
+
+        Ok(Some(buf.split().freeze()))
+    }
+}
+
+impl<T> Encoder<T> for DelimitedBytesCodec
+where
+    T: AsRef<[u8]>,
+{
+    type Error = DelimitedBytesCodecError;
+
+    fn encode(&mut self, frame: T, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        let frame = frame.as_ref();
+        buf.reserve(frame.len() + 1);
+        buf.put(frame);
+        buf.put_u8(self.delimiter);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DelimitedBytesCodecError {
+    #[error("max frame length exceeded")]
+    MaxLengthExceeded,
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_chunked() {
+        let mut codec = DelimitedBytesCodec::new(b'\n');
+        let mut buf = BytesMut::new();
+
+        buf.extend_from_slice(b"hel");
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(b"lo\n\xff\x00\nwor");
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Bytes::from("hello")));
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Bytes::from(&b"\xff\x00"[..]))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(b"ld");
+        assert_eq!(
+            codec.decode_eof(&mut buf).unwrap(),
+            Some(Bytes::from("world"))
+        );
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_decode_strict_eof() {
+        let mut codec = DelimitedBytesCodec::new(b'/').with_strict_eof(true);
+        let mut buf = BytesMut::from("a/b");
+
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), Some(Bytes::from("a")));
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_decode_max_length() {
+        let mut codec = DelimitedBytesCodec::new_with_max_length(b'\n', 3);
+        let mut buf = BytesMut::from("abcdef\nabc\n");
+
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(DelimitedBytesCodecError::MaxLengthExceeded)
+        ));
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Bytes::from("abc")));
+    }
+}
@@ -0,0 +1,336 @@
+use bytes::BytesMut;
+use std::{fmt, io};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Closure-based combinators available on every codec.
+///
+/// Each combinator only touches one direction and forwards the other one untouched, so the result
+/// can still be used with [`Framed`] as well as with split [`FramedRead`]/[`FramedWrite`] halves.
+///
+/// [`Framed`]: tokio_util::codec::Framed
+/// [`FramedRead`]: tokio_util::codec::FramedRead
+/// [`FramedWrite`]: tokio_util::codec::FramedWrite
+pub trait CodecExt: Sized {
+    /// Maps every decoded item through `f`.
+    fn map_decode<F, Item>(self, f: F) -> MapDecode<Self, F>
+    where
+        Self: Decoder,
+        F: FnMut(Self::Item) -> Item,
+    {
+        MapDecode { inner: self, f }
+    }
+
+    /// Maps every decoded item through the fallible `f`.
+    ///
+    /// The decoder error type is kept as-is, so `f`'s error must be convertible into it.
+    fn try_map_decode<F, Item, E>(self, f: F) -> TryMapDecode<Self, F>
+    where
+        Self: Decoder,
+        Self::Error: From<E>,
+        F: FnMut(Self::Item) -> Result<Item, E>,
+    {
+        TryMapDecode { inner: self, f }
+    }
+
+    /// Maps every item through `f` before handing it to the inner encoder.
+    fn map_encode<F>(self, f: F) -> MapEncode<Self, F> {
+        MapEncode { inner: self, f }
+    }
+
+    /// Calls `f` with a reference to every decoded item before yielding it.
+    fn inspect<F>(self, f: F) -> Inspect<Self, F>
+    where
+        Self: Decoder,
+        F: FnMut(&Self::Item),
+    {
+        Inspect { inner: self, f }
+    }
+
+    /// Maps decoder and encoder errors through `f`.
+    ///
+    /// A direction is only implemented if `f` accepts its error type, which is always the case
+    /// for codecs (like most in this crate) that use the same error type for both.
+    fn map_err<F>(self, f: F) -> MapErr<Self, F> {
+        MapErr { inner: self, f }
+    }
+}
+
+impl<C> CodecExt for C {}
+
+macro_rules! debug_inner {
+    ($name:ident) => {
+        impl<C: fmt::Debug, F> fmt::Debug for $name<C, F> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("inner", &self.inner)
+                    .finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+macro_rules! forward_decoder {
+    ($name:ident) => {
+        impl<C, F> Decoder for $name<C, F>
+        where
+            C: Decoder,
+        {
+            type Item = C::Item;
+            type Error = C::Error;
+
+            fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+                self.inner.decode(src)
+            }
+
+            fn decode_eof(
+                &mut self,
+                src: &mut BytesMut,
+            ) -> Result<Option<Self::Item>, Self::Error> {
+                self.inner.decode_eof(src)
+            }
+        }
+    };
+}
+
+macro_rules! forward_encoder {
+    ($name:ident) => {
+        impl<C, F, Item> Encoder<Item> for $name<C, F>
+        where
+            C: Encoder<Item>,
+        {
+            type Error = C::Error;
+
+            fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+                self.inner.encode(item, dst)
+            }
+        }
+    };
+}
+
+/// Codec returned by [`CodecExt::map_decode`].
+#[derive(Clone)]
+pub struct MapDecode<C, F> {
+    inner: C,
+    f: F,
+}
+
+debug_inner!(MapDecode);
+forward_encoder!(MapDecode);
+
+impl<C, F, Item> Decoder for MapDecode<C, F>
+where
+    C: Decoder,
+    F: FnMut(C::Item) -> Item,
+{
+    type Item = Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.inner.decode(src)?.map(&mut self.f))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.inner.decode_eof(src)?.map(&mut self.f))
+    }
+}
+
+/// Codec returned by [`CodecExt::try_map_decode`].
+#[derive(Clone)]
+pub struct TryMapDecode<C, F> {
+    inner: C,
+    f: F,
+}
+
+debug_inner!(TryMapDecode);
+forward_encoder!(TryMapDecode);
+
+impl<C, F, Item, E> Decoder for TryMapDecode<C, F>
+where
+    C: Decoder,
+    C::Error: From<E>,
+    F: FnMut(C::Item) -> Result<Item, E>,
+{
+    type Item = Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let Some(item) = self.inner.decode(src)? else {
+            return Ok(None);
+        };
+
+        Ok(Some((self.f)(item)?))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let Some(item) = self.inner.decode_eof(src)? else {
+            return Ok(None);
+        };
+
+        Ok(Some((self.f)(item)?))
+    }
+}
+
+/// Codec returned by [`CodecExt::map_encode`].
+#[derive(Clone)]
+pub struct MapEncode<C, F> {
+    inner: C,
+    f: F,
+}
+
+debug_inner!(MapEncode);
+forward_decoder!(MapEncode);
+
+impl<C, F, Item, EncItem> Encoder<Item> for MapEncode<C, F>
+where
+    C: Encoder<EncItem>,
+    F: FnMut(Item) -> EncItem,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode((self.f)(item), dst)
+    }
+}
+
+/// Codec returned by [`CodecExt::inspect`].
+#[derive(Clone)]
+pub struct Inspect<C, F> {
+    inner: C,
+    f: F,
+}
+
+debug_inner!(Inspect);
+forward_encoder!(Inspect);
+
+impl<C, F> Decoder for Inspect<C, F>
+where
+    C: Decoder,
+    F: FnMut(&C::Item),
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let item = self.inner.decode(src)?;
+
+        if let Some(item) = &item {
+            (self.f)(item);
+        }
+
+        Ok(item)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let item = self.inner.decode_eof(src)?;
+
+        if let Some(item) = &item {
+            (self.f)(item);
+        }
+
+        Ok(item)
+    }
+}
+
+/// Codec returned by [`CodecExt::map_err`].
+#[derive(Clone)]
+pub struct MapErr<C, F> {
+    inner: C,
+    f: F,
+}
+
+debug_inner!(MapErr);
+
+impl<C, F, E> Decoder for MapErr<C, F>
+where
+    C: Decoder,
+    F: FnMut(C::Error) -> E,
+    E: From<io::Error>,
+{
+    type Item = C::Item;
+    type Error = E;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode(src).map_err(&mut self.f)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner.decode_eof(src).map_err(&mut self.f)
+    }
+}
+
+impl<C, F, Item, E> Encoder<Item> for MapErr<C, F>
+where
+    C: Encoder<Item>,
+    F: FnMut(C::Error) -> E,
+    E: From<io::Error>,
+{
+    type Error = E;
+
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst).map_err(&mut self.f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedSizeCodec;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Odd(u8);
+
+    #[derive(Debug)]
+    struct EvenError;
+
+    impl From<EvenError> for io::Error {
+        fn from(_: EvenError) -> Self {
+            io::Error::new(io::ErrorKind::InvalidData, "even")
+        }
+    }
+
+    impl TryFrom<[u8; 1]> for Odd {
+        type Error = EvenError;
+
+        fn try_from([value]: [u8; 1]) -> Result<Self, Self::Error> {
+            if value % 2 == 1 {
+                Ok(Odd(value))
+            } else {
+                Err(EvenError)
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_map_decode() {
+        let mut codec = FixedSizeCodec::<1>::new().try_map_decode(Odd::try_from);
+        let mut src = BytesMut::from(&[3, 4][..]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(Odd(3)));
+        assert_eq!(
+            codec.decode(&mut src).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn test_chained() {
+        let mut seen = Vec::new();
+
+        let mut codec = FixedSizeCodec::<2>::new()
+            .map_decode(u16::from_be_bytes)
+            .inspect(|item| seen.push(*item))
+            .map_encode(u16::to_be_bytes)
+            .map_err(|err: io::Error| io::Error::new(io::ErrorKind::BrokenPipe, err));
+
+        let mut buf = BytesMut::new();
+        codec.encode(0x1234, &mut buf).unwrap();
+        codec.encode(0x5678, &mut buf).unwrap();
+
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(0x1234));
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(0x5678));
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        assert_eq!(seen, vec![0x1234, 0x5678]);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{inspect_bytes, inspect_item, is_inspect_enabled, Direction};
+use bytes::{Buf, BytesMut};
+use std::fmt::Debug;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Wraps a codec logging every decoded and encoded item, along with its raw bytes as a hexdump.
+///
+/// Logging can be toggled at runtime with [`set_inspect_enabled`](crate::set_inspect_enabled).
+#[derive(Debug)]
+pub struct InspectCodec<C> {
+    inner: C,
+    peer: String,
+    /// The decode buffer as of the last decode, to hexdump what the inner codec consumes. Only
+    /// kept while inspecting, and grown by what was received since rather than copied each time.
+    received: Option<BytesMut>,
+}
+
+impl<C> InspectCodec<C> {
+    pub fn new(inner: C, peer: impl ToString) -> Self {
+        Self {
+            inner,
+            peer: peer.to_string(),
+            received: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn decode_with<T: Debug, E: Debug>(
+        &mut self,
+        src: &mut BytesMut,
+        decode: impl FnOnce(&mut C, &mut BytesMut) -> Result<Option<T>, E>,
+    ) -> Result<Option<T>, E> {
+        if !is_inspect_enabled() {
+            self.received = None;
+            return decode(&mut self.inner, src);
+        }
+
+        // Bytes are only ever appended to the buffer between decodes, anything else resyncs
+        let mut received = match self.received.take() {
+            Some(mut received) if received.len() <= src.len() => {
+                received.extend_from_slice(&src[received.len()..]);
+                received
+            }
+            _ => src.clone(),
+        };
+
+        let result = decode(&mut self.inner, src);
+
+        match &result {
+            Ok(Some(item)) => {
+                inspect_item(&self.peer, Direction::Received, item);
+                let consumed = received.len().saturating_sub(src.len());
+                inspect_bytes(
+                    &self.peer,
+                    Direction::Received,
+                    &received.split_to(consumed),
+                );
+                self.received = Some(received);
+            }
+            Ok(None) => {
+                received.advance(received.len().saturating_sub(src.len()));
+                self.received = Some(received);
+            }
+            Err(err) => {
+                inspect_item(&self.peer, Direction::Received, err);
+                inspect_bytes(&self.peer, Direction::Received, &received);
+            }
+        }
+
+        result
+    }
+}
+
+impl<C> Decoder for InspectCodec<C>
+where
+    C: Decoder,
+    C::Item: Debug,
+    C::Error: Debug,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_with(src, C::decode)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_with(src, C::decode_eof)
+    }
+}
+
+impl<C, Item> Encoder<Item> for InspectCodec<C>
+where
+    C: Encoder<Item>,
+    Item: Debug,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        inspect_item(&self.peer, Direction::Sent, &item);
+
+        let start = dst.len();
+
+        self.inner.encode(item, dst)?;
+
+        inspect_bytes(&self.peer, Direction::Sent, &dst[start..]);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::set_inspect_enabled;
+    use tokio_util::codec::LinesCodec;
+
+    #[test]
+    fn test_received_follows_buffer() {
+        set_inspect_enabled(true);
+        let mut codec = InspectCodec::new(LinesCodec::new(), "test");
+        let mut src = BytesMut::new();
+
+        for (chunk, line) in [
+            ("hel", None),
+            ("lo\nwor", Some("hello")),
+            ("ld\n", Some("world")),
+        ] {
+            src.extend_from_slice(chunk.as_bytes());
+
+            assert_eq!(codec.decode(&mut src).unwrap().as_deref(), line);
+            assert_eq!(codec.received.as_ref(), Some(&src));
+        }
+    }
+}
@@ -1,23 +1,28 @@
-use bytes::BytesMut;
+use crate::{DelimitedBytesCodec, DelimitedBytesCodecError};
+use bytes::{BufMut, Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData};
 use thiserror::Error;
-use tokio_util::codec::{Decoder, Encoder, LinesCodec, LinesCodecError};
+use tokio_util::codec::{Decoder, Encoder};
 
 #[derive(Debug, Error)]
 pub enum JsonCodecError {
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
-    #[error("lines codec error: {0}")]
-    LinesCodec(#[from] LinesCodecError),
+    #[error("delimited codec error: {0}")]
+    Delimited(#[from] DelimitedBytesCodecError),
     #[error("de/serialization error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 }
 
-#[derive(Debug, Default)]
+/// JSON-lines codec.
+///
+/// Decodes into any owned `Dec` or, when `Dec` is [`JsonFrame`], into raw zero-copy line frames
+/// that can later be deserialized into borrowing types.
+#[derive(Debug)]
 pub struct JsonCodec<Dec, Enc> {
     // TODO: Allow any inner codec ?
-    lines_codec: LinesCodec,
+    lines_codec: DelimitedBytesCodec,
     dec: PhantomData<Dec>,
     enc: PhantomData<Enc>,
 }
@@ -25,13 +30,19 @@ pub struct JsonCodec<Dec, Enc> {
 impl<Dec, Enc> JsonCodec<Dec, Enc> {
     pub fn new() -> Self {
         Self {
-            lines_codec: LinesCodec::new(),
-            enc: PhantomData::default(),
-            dec: PhantomData::default(),
+            lines_codec: DelimitedBytesCodec::new(b'\n'),
+            enc: PhantomData,
+            dec: PhantomData,
         }
     }
 }
 
+impl<Dec, Enc> Default for JsonCodec<Dec, Enc> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Dec, Enc> Decoder for JsonCodec<Dec, Enc>
 where
     Dec: for<'de> Deserialize<'de>,
@@ -44,7 +55,16 @@ where
             return Ok(None);
         };
 
-        Ok(Some(serde_json::from_str::<Dec>(&line)?))
+        Ok(Some(serde_json::from_slice::<Dec>(&line)?))
+    }
+}
+
+impl<Enc> Decoder for JsonCodec<JsonFrame, Enc> {
+    type Item = JsonFrame;
+    type Error = JsonCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.lines_codec.decode(src)?.map(JsonFrame))
     }
 }
 
@@ -56,9 +76,36 @@ where
     type Error = JsonCodecError;
 
     fn encode(&mut self, item: AsRefEnc, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let item = item.as_ref();
-        let item = serde_json::to_string(item)?;
+        serde_json::to_writer(dst.writer(), item.as_ref())?;
+        dst.put_u8(b'\n');
+
+        Ok(())
+    }
+}
+
+/// A single undecoded JSON line, sharing the connection read buffer.
+///
+/// Deserializing from it allows borrowing `&str` and
+/// [`&RawValue`](serde_json::value::RawValue) straight from the received bytes.
+#[derive(Clone)]
+pub struct JsonFrame(Bytes);
+
+impl JsonFrame {
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.0)
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
 
-        Ok(self.lines_codec.encode(item, dst)?)
+impl fmt::Debug for JsonFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JsonFrame({})", String::from_utf8_lossy(&self.0))
     }
 }
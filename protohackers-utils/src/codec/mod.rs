@@ -1,11 +1,15 @@
+mod delimited_bytes;
 mod enc_dec;
+mod ext;
 mod fixed_size;
-mod from_into;
+mod inspect;
 mod json;
 mod strict_lines_codec;
 
+pub use delimited_bytes::*;
 pub use enc_dec::*;
+pub use ext::*;
 pub use fixed_size::*;
-pub use from_into::*;
+pub use inspect::*;
 pub use json::*;
 pub use strict_lines_codec::*;
@@ -1,5 +1,6 @@
-use bytes::{Buf, BufMut, BytesMut};
-use std::{cmp, fmt, io, str, usize};
+use crate::{DelimitedBytesCodec, DelimitedBytesCodecError};
+use bytes::BytesMut;
+use std::{fmt, io, str};
 use tokio_util::codec::{Decoder, Encoder};
 
 /// A simple [`Decoder`] and [`Encoder`] implementation that splits up data into lines.
@@ -7,25 +8,14 @@ use tokio_util::codec::{Decoder, Encoder};
 /// The difference with tokio_util::codec::LinesCodec is that this one will return `None`
 /// if there is a missing newline right before EOF.
 ///
+/// This is a UTF-8 layer on top of [`DelimitedBytesCodec`], use that one for binary data.
+///
 /// [`Decoder`]: crate::codec::Decoder
 /// [`Encoder`]: crate::codec::Encoder
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct StrictLinesCodec {
-    // Stored index of the next index to examine for a `\n` character.
-    // This is used to optimize searching.
-    // For example, if `decode` was called with `abc`, it would hold `3`,
-    // because that is the next index to examine.
-    // The next time `decode` is called with `abcde\n`, the method will
-    // only look at `de\n` before returning.
-    next_index: usize,
-
-    /// The maximum length for a given line. If `usize::MAX`, lines will be
-    /// read until a `\n` character is reached.
-    max_length: usize,
-
-    /// Are we currently discarding the remainder of a line which was over
-    /// the length limit?
-    is_discarding: bool,
+    /// Binary line splitter, this codec only adds UTF-8 validation on top.
+    inner: DelimitedBytesCodec,
 }
 
 impl StrictLinesCodec {
@@ -40,9 +30,7 @@ impl StrictLinesCodec {
     /// [`new_with_max_length`]: crate::codec::LinesCodec::new_with_max_length()
     pub fn new() -> StrictLinesCodec {
         StrictLinesCodec {
-            next_index: 0,
-            max_length: usize::MAX,
-            is_discarding: false,
+            inner: DelimitedBytesCodec::new(b'\n').with_strict_eof(true),
         }
     }
 
@@ -66,8 +54,8 @@ impl StrictLinesCodec {
     /// [`LinesCodecError`]: crate::codec::LinesCodecError
     pub fn new_with_max_length(max_length: usize) -> Self {
         StrictLinesCodec {
-            max_length,
-            ..StrictLinesCodec::new()
+            inner: DelimitedBytesCodec::new_with_max_length(b'\n', max_length)
+                .with_strict_eof(true),
         }
     }
 
@@ -87,7 +75,7 @@ impl StrictLinesCodec {
     /// assert_eq!(codec.max_length(), 256);
     /// ```
     pub fn max_length(&self) -> usize {
-        self.max_length
+        self.inner.max_length()
     }
 }
 
@@ -109,59 +97,19 @@ impl Decoder for StrictLinesCodec {
     type Error = LinesCodecError;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
-        loop {
-            // Determine how far into the buffer we'll search for a newline. If
-            // there's no max_length set, we'll read to the end of the buffer.
-            let read_to = cmp::min(self.max_length.saturating_add(1), buf.len());
-
-            let newline_offset = buf[self.next_index..read_to]
-                .iter()
-                .position(|b| *b == b'\n');
-
-            match (self.is_discarding, newline_offset) {
-                (true, Some(offset)) => {
-                    // If we found a newline, discard up to that offset and
-                    // then stop discarding. On the next iteration, we'll try
-                    // to read a line normally.
-                    buf.advance(offset + self.next_index + 1);
-                    self.is_discarding = false;
-                    self.next_index = 0;
-                }
-                (true, None) => {
-                    // Otherwise, we didn't find a newline, so we'll discard
-                    // everything we read. On the next iteration, we'll continue
-                    // discarding up to max_len bytes unless we find a newline.
-                    buf.advance(read_to);
-                    self.next_index = 0;
-                    if buf.is_empty() {
-                        return Ok(None);
-                    }
-                }
-                (false, Some(offset)) => {
-                    // Found a line!
-                    let newline_index = offset + self.next_index;
-                    self.next_index = 0;
-                    let line = buf.split_to(newline_index + 1);
-                    let line = &line[..line.len() - 1];
-                    let line = without_carriage_return(line);
-                    let line = utf8(line)?;
-                    return Ok(Some(line.to_string()));
-                }
-                (false, None) if buf.len() > self.max_length => {
-                    // Reached the maximum length without finding a
-                    // newline, return an error and start discarding on the
-                    // next call.
-                    self.is_discarding = true;
-                    return Err(LinesCodecError::MaxLineLengthExceeded);
-                }
-                (false, None) => {
-                    // We didn't find a line or reach the length limit, so the next
-                    // call will resume searching at the current offset.
-                    self.next_index = read_to;
-                    return Ok(None);
-                }
-            }
-        }
+        let Some(line) = self.inner.decode(buf)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(utf8(without_carriage_return(&line))?.to_string()))
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<String>, LinesCodecError> {
+        let Some(line) = self.inner.decode_eof(buf)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(utf8(without_carriage_return(&line))?.to_string()))
     }
 }
 
@@ -172,11 +120,7 @@ where
     type Error = LinesCodecError;
 
     fn encode(&mut self, line: T, buf: &mut BytesMut) -> Result<(), LinesCodecError> {
-        let line = line.as_ref();
-        buf.reserve(line.len() + 1);
-        buf.put(line.as_bytes());
-        buf.put_u8(b'\n');
-        Ok(())
+        Ok(self.inner.encode(line.as_ref().as_bytes(), buf)?)
     }
 }
 
@@ -210,4 +154,13 @@ impl From<io::Error> for LinesCodecError {
     }
 }
 
+impl From<DelimitedBytesCodecError> for LinesCodecError {
+    fn from(e: DelimitedBytesCodecError) -> LinesCodecError {
+        match e {
+            DelimitedBytesCodecError::MaxLengthExceeded => LinesCodecError::MaxLineLengthExceeded,
+            DelimitedBytesCodecError::Io(e) => LinesCodecError::Io(e),
+        }
+    }
+}
+
 impl std::error::Error for LinesCodecError {}
@@ -0,0 +1,295 @@
+use crate::TaskGroup;
+use futures::Future;
+use pin_project_lite::pin_project;
+use std::{
+    fmt::{self, Display},
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
+    time::SystemTime,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf, ReadHalf, WriteHalf},
+        TcpStream,
+    },
+    task::JoinHandle,
+};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+/// Process-wide unique identifier of a connection.
+pub type ConnectionId = u64;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Default)]
+struct ByteCounters {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+#[derive(Debug)]
+struct ContextInner {
+    id: ConnectionId,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+    accepted_at: SystemTime,
+    cancellation_token: CancellationToken,
+    counters: Arc<ByteCounters>,
+}
+
+/// Everything a handler knows about the connection it serves.
+///
+/// Cheap to clone, all the clones share the same cancellation token and byte counters. The
+/// connection is cancelled by [`default_tcp_listen`](crate::default_tcp_listen) once its handler
+/// returns, which stops the tasks started with [`ConnectionContext::spawn`].
+#[derive(Debug, Clone)]
+pub struct ConnectionContext(Arc<ContextInner>);
+
+impl ConnectionContext {
+    pub fn new(peer_addr: SocketAddr, local_addr: SocketAddr) -> Self {
+        Self(Arc::new(ContextInner {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            peer_addr,
+            local_addr,
+            accepted_at: SystemTime::now(),
+            cancellation_token: CancellationToken::new(),
+            counters: Arc::default(),
+        }))
+    }
+
+    pub fn id(&self) -> ConnectionId {
+        self.0.id
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.0.peer_addr
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.0.local_addr
+    }
+
+    pub fn accepted_at(&self) -> SystemTime {
+        self.0.accepted_at
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.0.cancellation_token
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancellation_token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancellation_token.is_cancelled()
+    }
+
+    /// Waits until the connection is cancelled.
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.0.cancellation_token.cancelled()
+    }
+
+    /// Total bytes read from the connection so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.0.counters.read.load(Ordering::Relaxed)
+    }
+
+    /// Total bytes written to the connection so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.0.counters.written.load(Ordering::Relaxed)
+    }
+
+    /// Spawns a child task that is stopped when the connection is cancelled.
+    ///
+    /// The task resolves to `None` if it got cancelled before completing.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let cancellation_token = self.0.cancellation_token.clone();
+
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => None,
+                output = future => Some(output),
+            }
+        })
+    }
+
+    /// Returns a new [`TaskGroup`] cancelled along with the connection.
+    pub fn task_group<E>(&self) -> TaskGroup<E>
+    where
+        E: Send + 'static,
+    {
+        TaskGroup::with_cancellation_token(self.0.cancellation_token.child_token())
+    }
+
+    /// Wraps `stream` so that its traffic is accounted in this connection's byte counters.
+    pub fn wrap_stream<S>(&self, stream: S) -> ConnectionStream<S> {
+        ConnectionStream {
+            inner: stream,
+            counters: Arc::clone(&self.0.counters),
+        }
+    }
+}
+
+impl Display for ConnectionContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.0.peer_addr, self.0.id)
+    }
+}
+
+pin_project! {
+    /// A stream counting the bytes going through it into its [`ConnectionContext`].
+    #[derive(Debug)]
+    pub struct ConnectionStream<S = TcpStream> {
+        #[pin]
+        inner: S,
+        counters: Arc<ByteCounters>,
+    }
+}
+
+impl<S> ConnectionStream<S> {
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl ConnectionStream<TcpStream> {
+    /// Like [`TcpStream::split`], keeping the byte counting on both halves.
+    pub fn split(
+        &mut self,
+    ) -> (
+        ConnectionStream<ReadHalf<'_>>,
+        ConnectionStream<WriteHalf<'_>>,
+    ) {
+        let (read, write) = self.inner.split();
+
+        (
+            ConnectionStream {
+                inner: read,
+                counters: Arc::clone(&self.counters),
+            },
+            ConnectionStream {
+                inner: write,
+                counters: Arc::clone(&self.counters),
+            },
+        )
+    }
+
+    /// Like [`TcpStream::into_split`], keeping the byte counting on both halves.
+    pub fn into_split(
+        self,
+    ) -> (
+        ConnectionStream<OwnedReadHalf>,
+        ConnectionStream<OwnedWriteHalf>,
+    ) {
+        let (read, write) = self.inner.into_split();
+
+        (
+            ConnectionStream {
+                inner: read,
+                counters: Arc::clone(&self.counters),
+            },
+            ConnectionStream {
+                inner: write,
+                counters: self.counters,
+            },
+        )
+    }
+}
+
+impl<S> AsyncRead for ConnectionStream<S>
+where
+    S: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled_before = buf.filled().len();
+
+        ready!(this.inner.poll_read(cx, buf))?;
+
+        let read = buf.filled().len() - filled_before;
+        this.counters.read.fetch_add(read as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncWrite for ConnectionStream<S>
+where
+    S: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+
+        let written = ready!(this.inner.poll_write(cx, buf))?;
+        this.counters
+            .written
+            .fetch_add(written as u64, Ordering::Relaxed);
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_byte_counters_and_spawn() {
+        let addr = "127.0.0.1:1337".parse().unwrap();
+        let ctx = ConnectionContext::new(addr, addr);
+
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut client = ctx.wrap_stream(client);
+
+        client.write_all(b"hello").await.unwrap();
+        server.write_all(b"hi").await.unwrap();
+
+        let mut buf = [0; 2];
+        client.read_exact(&mut buf).await.unwrap();
+
+        assert_eq!(ctx.bytes_written(), 5);
+        assert_eq!(ctx.bytes_read(), 2);
+
+        let child = ctx.spawn(futures::future::pending::<()>());
+        ctx.cancel();
+
+        assert_eq!(child.await.unwrap(), None);
+    }
+}
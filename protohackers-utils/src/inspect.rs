@@ -0,0 +1,152 @@
+use std::{
+    fmt::{self, Debug, Display},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// Environment variable used to toggle inspection at startup (`1`/`true`/`on` or `0`/`false`/`off`).
+///
+/// When unset, inspection is enabled in debug builds only.
+pub const INSPECT_ENV_VAR: &str = "PROTOHACKERS_INSPECT";
+
+const UNINIT: u8 = 0;
+const DISABLED: u8 = 1;
+const ENABLED: u8 = 2;
+
+static INSPECT_STATE: AtomicU8 = AtomicU8::new(UNINIT);
+
+/// Returns whether frame inspection is currently enabled.
+pub fn is_inspect_enabled() -> bool {
+    match INSPECT_STATE.load(Ordering::Relaxed) {
+        UNINIT => {
+            let enabled = match std::env::var(INSPECT_ENV_VAR).as_deref() {
+                Ok("1" | "true" | "on") => true,
+                Ok("0" | "false" | "off") => false,
+                _ => cfg!(debug_assertions),
+            };
+
+            // Lose the race gracefully if someone called `set_inspect_enabled` meanwhile
+            let _ = INSPECT_STATE.compare_exchange(
+                UNINIT,
+                if enabled { ENABLED } else { DISABLED },
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+
+            INSPECT_STATE.load(Ordering::Relaxed) == ENABLED
+        }
+        state => state == ENABLED,
+    }
+}
+
+/// Enables or disables frame inspection at runtime.
+pub fn set_inspect_enabled(enabled: bool) {
+    INSPECT_STATE.store(if enabled { ENABLED } else { DISABLED }, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Received,
+    Sent,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Received => f.write_str("-->"),
+            Direction::Sent => f.write_str("<--"),
+        }
+    }
+}
+
+/// Logs a decoded or to-be-encoded item, if inspection is enabled.
+pub fn inspect_item<T: Debug + ?Sized>(peer: impl Display, direction: Direction, item: &T) {
+    if is_inspect_enabled() {
+        println!("{peer} {direction} {item:?}");
+    }
+}
+
+/// Logs raw bytes as a hexdump, if inspection is enabled.
+pub fn inspect_bytes(peer: impl Display, direction: Direction, bytes: &[u8]) {
+    if is_inspect_enabled() && !bytes.is_empty() {
+        for line in HexDump(bytes).lines() {
+            println!("{peer} {direction} {line}");
+        }
+    }
+}
+
+/// Formats bytes like `hexdump -C` does: offset, 16 hex bytes and their printable ASCII.
+#[derive(Clone, Copy)]
+pub struct HexDump<'a>(pub &'a [u8]);
+
+impl<'a> HexDump<'a> {
+    const BYTES_PER_LINE: usize = 16;
+
+    pub fn lines(&self) -> impl Iterator<Item = String> + 'a {
+        self.0
+            .chunks(Self::BYTES_PER_LINE)
+            .enumerate()
+            .map(|(i, chunk)| format_line(i * Self::BYTES_PER_LINE, chunk))
+    }
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+
+            f.write_str(&line)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_line(offset: usize, chunk: &[u8]) -> String {
+    let mut line = format!("{offset:08x} ");
+
+    for i in 0..HexDump::BYTES_PER_LINE {
+        if i % 8 == 0 {
+            line.push(' ');
+        }
+
+        match chunk.get(i) {
+            Some(byte) => line.push_str(&format!("{byte:02x} ")),
+            None => line.push_str("   "),
+        }
+    }
+
+    line.push_str(" |");
+    line.extend(chunk.iter().map(|&byte| {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            byte as char
+        } else {
+            '.'
+        }
+    }));
+    line.push('|');
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump() {
+        assert_eq!(HexDump(b"").to_string(), "");
+
+        assert_eq!(
+            HexDump(b"Hello\n").to_string(),
+            "00000000  48 65 6c 6c 6f 0a                                 |Hello.|"
+        );
+
+        assert_eq!(
+            HexDump(b"0123456789abcdef\x00\xff").to_string(),
+            "00000000  30 31 32 33 34 35 36 37  38 39 61 62 63 64 65 66  |0123456789abcdef|\n\
+             00000010  00 ff                                             |..|"
+        );
+    }
+}
@@ -1,8 +1,24 @@
 mod codec;
+mod connection;
+mod inspect;
 mod listen;
+mod net;
+mod outbound;
+mod rate_limit;
+mod serve;
+mod task_group;
+mod transform;
 
 pub use codec::*;
+pub use connection::*;
+pub use inspect::*;
 pub use listen::*;
+pub use net::*;
+pub use outbound::*;
+pub use rate_limit::*;
+pub use serve::*;
+pub use task_group::*;
+pub use transform::*;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
 
@@ -1,10 +1,8 @@
-use futures::Future;
-use std::net::SocketAddr;
+use crate::{ConnectionContext, ConnectionStream, Listener, NetStream};
+use futures::{stream, Future, Stream};
+use std::net::{Ipv4Addr, Ipv6Addr};
 use thiserror::Error;
-use tokio::{
-    net::{TcpListener, TcpStream},
-    task::JoinHandle,
-};
+use tokio::{net::TcpListener, task::JoinHandle};
 use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
 
 #[derive(Debug, Error)]
@@ -13,46 +11,92 @@ pub enum ListenError {
     Io(#[from] std::io::Error),
 }
 
+pub const DEFAULT_PORT: u16 = 1337;
 pub const DEFAULT_IPV4_ADDR: &str = "0.0.0.0:1337";
 pub const DEFAULT_IPV6_ADDR: &str = "[::]:1337";
 
 pub async fn default_tcp_listen<F, Fut, E>(handle_client: F) -> std::io::Result<()>
 where
-    F: Fn(TcpStream, SocketAddr) -> Fut,
+    F: Fn(ConnectionStream, ConnectionContext) -> Fut,
     Fut: Future<Output = Result<(), E>> + Send + 'static,
     E: Send + 'static,
 {
-    let listener_v4 = TcpListener::bind(DEFAULT_IPV4_ADDR).await?;
-    let listener_v6 = TcpListener::bind(DEFAULT_IPV6_ADDR).await?;
+    tcp_listen_on_port(DEFAULT_PORT, handle_client).await
+}
+
+/// Like [`default_tcp_listen`], on another port.
+pub async fn tcp_listen_on_port<F, Fut, E>(port: u16, handle_client: F) -> std::io::Result<()>
+where
+    F: Fn(ConnectionStream, ConnectionContext) -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Send + 'static,
+{
+    let listener_v4 = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).await?;
+    let listener_v6 = TcpListener::bind((Ipv6Addr::UNSPECIFIED, port)).await?;
 
     let local_addr_v4 = listener_v4.local_addr()?;
     let local_addr_v6 = listener_v6.local_addr()?;
 
     println!("Listening on {local_addr_v4} (v4) {local_addr_v6} (v6)");
 
-    let mut incoming =
-        TcpListenerStream::new(listener_v4).merge(TcpListenerStream::new(listener_v6));
+    let incoming = TcpListenerStream::new(listener_v4).merge(TcpListenerStream::new(listener_v6));
 
+    serve_incoming(incoming, handle_client).await
+}
+
+/// Serves the connections accepted by `listener`, which may be a simulated one.
+pub async fn listen<L, F, Fut, E>(listener: L, handle_client: F) -> std::io::Result<()>
+where
+    L: Listener,
+    F: Fn(ConnectionStream<L::Stream>, ConnectionContext) -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Send + 'static,
+{
+    println!("Listening on {}", listener.local_addr()?);
+
+    let incoming = stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| stream);
+        Some((stream, listener))
+    });
+
+    serve_incoming(Box::pin(incoming), handle_client).await
+}
+
+async fn serve_incoming<I, S, F, Fut, E>(mut incoming: I, handle_client: F) -> std::io::Result<()>
+where
+    I: Stream<Item = std::io::Result<S>> + Unpin,
+    S: NetStream,
+    F: Fn(ConnectionStream<S>, ConnectionContext) -> Fut,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Send + 'static,
+{
     while let Some(stream) = incoming.next().await {
         let stream = stream?;
 
-        let addr = stream.peer_addr()?;
+        let ctx = ConnectionContext::new(stream.peer_addr()?, stream.local_addr()?);
 
-        println!("Got a connection from {addr}");
+        println!("Got a connection from {ctx}");
 
         stream.set_nodelay(true)?;
         // TODO: Maybe use:
         // stream.set_linger(dur)?;
         // stream.set_ttl(ttl)?;
 
-        let client_future = handle_client(stream, addr);
+        let client_future = handle_client(ctx.wrap_stream(stream), ctx.clone());
 
         let _: JoinHandle<Result<(), E>> = tokio::spawn(async move {
-            client_future.await?;
+            let result = client_future.await;
+
+            // Stop the tasks spawned for this connection
+            ctx.cancel();
 
-            println!("Client {addr} disconnected");
+            println!(
+                "Client {ctx} disconnected ({} bytes read, {} bytes written)",
+                ctx.bytes_read(),
+                ctx.bytes_written()
+            );
 
-            Ok(())
+            result
         });
     }
 
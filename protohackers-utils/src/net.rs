@@ -0,0 +1,178 @@
+use futures::future::BoxFuture;
+use std::{fmt::Debug, io, net::SocketAddr};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// A listener accepting [`NetStream`]s, so servers can run over real sockets or a simulated
+/// network.
+pub trait Listener: Send + Sync + 'static {
+    type Stream: NetStream;
+
+    fn accept(&self) -> BoxFuture<'_, io::Result<(Self::Stream, SocketAddr)>>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// A connected byte stream, as accepted by a [`Listener`].
+pub trait NetStream: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    fn set_nodelay(&self, nodelay: bool) -> io::Result<()>;
+}
+
+/// A socket sending and receiving datagrams, so servers can run over real sockets or a simulated
+/// network.
+pub trait DatagramSocket: Debug + Send + Sync + 'static {
+    fn send_to<'a>(&'a self, buf: &'a [u8], target: SocketAddr)
+        -> BoxFuture<'a, io::Result<usize>>;
+
+    fn recv_from<'a>(&'a self, buf: &'a mut [u8])
+        -> BoxFuture<'a, io::Result<(usize, SocketAddr)>>;
+
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl Listener for tokio::net::TcpListener {
+    type Stream = tokio::net::TcpStream;
+
+    fn accept(&self) -> BoxFuture<'_, io::Result<(Self::Stream, SocketAddr)>> {
+        Box::pin(self.accept())
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.local_addr()
+    }
+}
+
+impl NetStream for tokio::net::TcpStream {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.peer_addr()
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.local_addr()
+    }
+
+    fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.set_nodelay(nodelay)
+    }
+}
+
+impl DatagramSocket for tokio::net::UdpSocket {
+    fn send_to<'a>(
+        &'a self,
+        buf: &'a [u8],
+        target: SocketAddr,
+    ) -> BoxFuture<'a, io::Result<usize>> {
+        Box::pin(self.send_to(buf, target))
+    }
+
+    fn recv_from<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+    ) -> BoxFuture<'a, io::Result<(usize, SocketAddr)>> {
+        Box::pin(self.recv_from(buf))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.local_addr()
+    }
+}
+
+#[cfg(feature = "turmoil")]
+mod sim {
+    use super::{DatagramSocket, Listener, NetStream};
+    use futures::future::BoxFuture;
+    use std::{
+        io,
+        net::SocketAddr,
+        ops::Range,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    /// Environment variable replaying a single simulation seed, see [`run_seeded`].
+    pub const SIM_SEED_ENV_VAR: &str = "PROTOHACKERS_SIM_SEED";
+
+    /// Runs `simulation` once per seed in `seeds`, or only for the seed in [`SIM_SEED_ENV_VAR`] if
+    /// set. Panics on the first failing seed, naming it so the failure can be replayed.
+    pub fn run_seeded<F>(seeds: Range<u64>, mut simulation: F)
+    where
+        F: FnMut(u64) -> turmoil::Result,
+    {
+        let seeds = match std::env::var(SIM_SEED_ENV_VAR) {
+            Ok(seed) => {
+                let seed = seed
+                    .parse::<u64>()
+                    .unwrap_or_else(|_| panic!("{SIM_SEED_ENV_VAR} must be a u64, got {seed:?}"));
+                seed..seed + 1
+            }
+            Err(_) => seeds,
+        };
+
+        for seed in seeds {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| simulation(seed)));
+
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    panic!("simulation failed with seed {seed}, replay with {SIM_SEED_ENV_VAR}={seed}: {err}")
+                }
+                Err(panic) => {
+                    eprintln!("simulation panicked with seed {seed}, replay with {SIM_SEED_ENV_VAR}={seed}");
+                    panic::resume_unwind(panic);
+                }
+            }
+        }
+    }
+
+    impl Listener for turmoil::net::TcpListener {
+        type Stream = turmoil::net::TcpStream;
+
+        fn accept(&self) -> BoxFuture<'_, io::Result<(Self::Stream, SocketAddr)>> {
+            Box::pin(self.accept())
+        }
+
+        fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.local_addr()
+        }
+    }
+
+    impl NetStream for turmoil::net::TcpStream {
+        fn peer_addr(&self) -> io::Result<SocketAddr> {
+            self.peer_addr()
+        }
+
+        fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.local_addr()
+        }
+
+        fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+            self.set_nodelay(nodelay)
+        }
+    }
+
+    impl DatagramSocket for turmoil::net::UdpSocket {
+        fn send_to<'a>(
+            &'a self,
+            buf: &'a [u8],
+            target: SocketAddr,
+        ) -> BoxFuture<'a, io::Result<usize>> {
+            Box::pin(self.send_to(buf, target))
+        }
+
+        fn recv_from<'a>(
+            &'a self,
+            buf: &'a mut [u8],
+        ) -> BoxFuture<'a, io::Result<(usize, SocketAddr)>> {
+            Box::pin(self.recv_from(buf))
+        }
+
+        fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.local_addr()
+        }
+    }
+}
+
+#[cfg(feature = "turmoil")]
+pub use sim::*;
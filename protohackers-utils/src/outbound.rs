@@ -0,0 +1,343 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    sync::{Arc, Mutex},
+};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+/// What to do when a consumer doesn't drain its outbound queue fast enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlowConsumerPolicy {
+    /// Drop all the queued messages and make the receiver fail with [`OutboundOverflowError`].
+    Disconnect,
+    /// Drop the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Wait for room in [`OutboundSender::send`], fail with [`OutboundSendError::Full`] in
+    /// [`OutboundSender::try_send`].
+    Block,
+}
+
+#[derive(Debug, Error)]
+pub enum OutboundSendError<T> {
+    #[error("outbound queue is full")]
+    Full(T),
+    #[error("outbound queue overflowed, the consumer is being disconnected")]
+    Overflowed(T),
+    #[error("outbound queue is closed")]
+    Closed(T),
+}
+
+impl<T> OutboundSendError<T> {
+    /// Returns the message that couldn't be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(item) | Self::Overflowed(item) | Self::Closed(item) => item,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("outbound queue overflowed, the consumer is too slow")]
+pub struct OutboundOverflowError;
+
+/// Counters of an outbound queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutboundMetrics {
+    /// Messages accepted into the queue.
+    pub enqueued: u64,
+    /// Messages handed to the receiver.
+    pub delivered: u64,
+    /// Messages dropped because of the [`SlowConsumerPolicy`].
+    pub dropped: u64,
+    /// Highest number of messages queued at once.
+    pub max_len: usize,
+    /// Whether the queue overflowed under [`SlowConsumerPolicy::Disconnect`].
+    pub overflowed: bool,
+}
+
+impl Display for OutboundMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} enqueued, {} delivered, {} dropped, max length {}",
+            self.enqueued, self.delivered, self.dropped, self.max_len
+        )?;
+
+        if self.overflowed {
+            f.write_str(", overflowed")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct State<T> {
+    queue: VecDeque<T>,
+    senders: usize,
+    is_receiver_closed: bool,
+    metrics: OutboundMetrics,
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    capacity: usize,
+    policy: SlowConsumerPolicy,
+    state: Mutex<State<T>>,
+    item_available: Notify,
+    space_available: Notify,
+}
+
+/// Creates a bounded queue of messages waiting to be written to a connection.
+///
+/// Unlike [`tokio::sync::mpsc`], what happens once `capacity` is reached is decided by `policy`,
+/// so a client that stops reading can't make the server buffer without limit nor stall the
+/// senders, unless asked to.
+///
+/// # Panics
+///
+/// If `capacity` is zero.
+pub fn outbound_queue<T>(
+    capacity: usize,
+    policy: SlowConsumerPolicy,
+) -> (OutboundSender<T>, OutboundReceiver<T>) {
+    assert!(capacity > 0, "outbound queue capacity must be at least 1");
+
+    let shared = Arc::new(Shared {
+        capacity,
+        policy,
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(capacity),
+            senders: 1,
+            is_receiver_closed: false,
+            metrics: OutboundMetrics::default(),
+        }),
+        item_available: Notify::new(),
+        space_available: Notify::new(),
+    });
+
+    (
+        OutboundSender {
+            shared: Arc::clone(&shared),
+        },
+        OutboundReceiver { shared },
+    )
+}
+
+#[derive(Debug)]
+pub struct OutboundSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OutboundSender<T> {
+    /// Queues `item` without waiting, applying the [`SlowConsumerPolicy`] if the queue is full.
+    pub fn try_send(&self, item: T) -> Result<(), OutboundSendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if state.is_receiver_closed || state.metrics.overflowed {
+            return Err(OutboundSendError::Closed(item));
+        }
+
+        if state.queue.len() >= self.shared.capacity {
+            match self.shared.policy {
+                SlowConsumerPolicy::Disconnect => {
+                    state.metrics.dropped += state.queue.len() as u64 + 1;
+                    state.metrics.overflowed = true;
+                    state.queue.clear();
+
+                    // Let the receiver know it has been cut off
+                    self.shared.item_available.notify_one();
+
+                    return Err(OutboundSendError::Overflowed(item));
+                }
+                SlowConsumerPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.metrics.dropped += 1;
+                }
+                SlowConsumerPolicy::Block => return Err(OutboundSendError::Full(item)),
+            }
+        }
+
+        state.queue.push_back(item);
+        state.metrics.enqueued += 1;
+        state.metrics.max_len = state.metrics.max_len.max(state.queue.len());
+
+        self.shared.item_available.notify_one();
+
+        Ok(())
+    }
+
+    /// Queues `item`, waiting for room if the policy is [`SlowConsumerPolicy::Block`].
+    pub async fn send(&self, mut item: T) -> Result<(), OutboundSendError<T>> {
+        loop {
+            // Created before trying so that a notification in between isn't missed
+            let space_available = self.shared.space_available.notified();
+
+            match self.try_send(item) {
+                Err(OutboundSendError::Full(returned)) => {
+                    item = returned;
+                    space_available.await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Waits until the queue has room or is closed. Unlike [`Self::send`], there's no message to
+    /// lose when cancelled.
+    pub async fn ready(&self) {
+        loop {
+            let space_available = self.shared.space_available.notified();
+
+            {
+                let state = self.shared.state.lock().unwrap();
+
+                if state.is_receiver_closed
+                    || state.metrics.overflowed
+                    || state.queue.len() < self.shared.capacity
+                {
+                    // The room may be left unused, pass the wakeup on to another waiting sender
+                    self.shared.space_available.notify_one();
+                    return;
+                }
+            }
+
+            space_available.await;
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.is_receiver_closed || state.metrics.overflowed
+    }
+
+    pub fn metrics(&self) -> OutboundMetrics {
+        self.shared.state.lock().unwrap().metrics
+    }
+}
+
+impl<T> Clone for OutboundSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for OutboundSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            self.shared.item_available.notify_one();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OutboundReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> OutboundReceiver<T> {
+    /// Receives the next message, or `None` once all the senders are dropped and the queue is
+    /// drained.
+    pub async fn recv(&mut self) -> Result<Option<T>, OutboundOverflowError> {
+        loop {
+            {
+                let mut state = self.shared.state.lock().unwrap();
+
+                if state.metrics.overflowed {
+                    return Err(OutboundOverflowError);
+                }
+
+                if let Some(item) = state.queue.pop_front() {
+                    state.metrics.delivered += 1;
+                    self.shared.space_available.notify_one();
+
+                    return Ok(Some(item));
+                }
+
+                if state.senders == 0 {
+                    return Ok(None);
+                }
+            }
+
+            // `notify_one` stores a permit, so a notification sent since the check isn't missed
+            self.shared.item_available.notified().await;
+        }
+    }
+
+    pub fn metrics(&self) -> OutboundMetrics {
+        self.shared.state.lock().unwrap().metrics
+    }
+}
+
+impl<T> Drop for OutboundReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().is_receiver_closed = true;
+        self.shared.space_available.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (send, mut recv) = outbound_queue(2, SlowConsumerPolicy::DropOldest);
+
+        for i in 0..4 {
+            send.try_send(i).unwrap();
+        }
+        drop(send);
+
+        assert_eq!(recv.recv().await.unwrap(), Some(2));
+        assert_eq!(recv.recv().await.unwrap(), Some(3));
+        assert_eq!(recv.recv().await.unwrap(), None);
+        assert_eq!(recv.metrics().dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let (send, mut recv) = outbound_queue(1, SlowConsumerPolicy::Disconnect);
+
+        send.try_send(1).unwrap();
+        assert!(matches!(
+            send.try_send(2),
+            Err(OutboundSendError::Overflowed(2))
+        ));
+        assert!(matches!(
+            send.try_send(3),
+            Err(OutboundSendError::Closed(3))
+        ));
+
+        assert!(recv.recv().await.is_err());
+        assert!(recv.metrics().overflowed);
+    }
+
+    #[tokio::test]
+    async fn test_block() {
+        let (send, mut recv) = outbound_queue(1, SlowConsumerPolicy::Block);
+
+        send.try_send(1).unwrap();
+        assert!(matches!(send.try_send(2), Err(OutboundSendError::Full(2))));
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), send.ready())
+                .await
+                .is_err()
+        );
+
+        let blocked = tokio::spawn(async move { send.send(2).await.map_err(|_| ()) });
+
+        assert_eq!(recv.recv().await.unwrap(), Some(1));
+        assert_eq!(recv.recv().await.unwrap(), Some(2));
+        assert_eq!(recv.recv().await.unwrap(), None);
+        assert!(blocked.await.unwrap().is_ok());
+    }
+}
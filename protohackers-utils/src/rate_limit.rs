@@ -0,0 +1,342 @@
+use futures::{Sink, Stream};
+use pin_project_lite::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    net::SocketAddr,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time::{Instant, Sleep};
+
+/// Token bucket parameters: up to `burst` frames at once, then one more every `refill_interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    burst: u32,
+    refill_interval: Duration,
+}
+
+impl RateLimit {
+    /// # Panics
+    ///
+    /// If `burst` is zero.
+    pub fn new(burst: u32, refill_interval: Duration) -> Self {
+        assert!(burst > 0, "burst must be at least 1");
+
+        Self {
+            burst,
+            refill_interval,
+        }
+    }
+
+    /// Returns a `RateLimit` refilling `per_second` tokens every second.
+    pub fn per_second(burst: u32, per_second: u32) -> Self {
+        Self::new(burst, Duration::from_secs(1) / per_second.max(1))
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    pub fn refill_interval(&self) -> Duration {
+        self.refill_interval
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Returns a full `TokenBucket`.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, or returns how long to wait until the next one is available.
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+
+        self.refill(now);
+
+        if self.tokens == 0 {
+            return Err(self.limit.refill_interval - (now - self.last_refill));
+        }
+
+        self.tokens -= 1;
+
+        Ok(())
+    }
+
+    /// Whether the bucket would be full right now, i.e. it has been idle long enough.
+    pub fn is_full(&self) -> bool {
+        let mut bucket = self.clone();
+        bucket.refill(Instant::now());
+
+        bucket.tokens == bucket.limit.burst
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if self.tokens == self.limit.burst || self.limit.refill_interval.is_zero() {
+            self.tokens = self.limit.burst;
+            self.last_refill = now;
+            return;
+        }
+
+        let elapsed = now - self.last_refill;
+        let new_tokens = elapsed.as_nanos() / self.limit.refill_interval.as_nanos();
+
+        if new_tokens == 0 {
+            return;
+        }
+
+        let new_tokens = u32::try_from(new_tokens).unwrap_or(u32::MAX);
+        self.tokens = self.tokens.saturating_add(new_tokens).min(self.limit.burst);
+        self.last_refill = if self.tokens == self.limit.burst {
+            now
+        } else {
+            self.last_refill + self.limit.refill_interval * new_tokens
+        };
+    }
+}
+
+/// What to do with a frame received over the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitPolicy {
+    /// Stop reading until a token is available.
+    Delay,
+    /// Silently drop the frame.
+    Drop,
+    /// Yield [`RateLimitError::Exceeded`] and end the stream, so the connection can be closed
+    /// after answering with a protocol error.
+    Close,
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitError<E> {
+    #[error("rate limit exceeded")]
+    Exceeded,
+    #[error("{0}")]
+    Inner(E),
+}
+
+pin_project! {
+    /// Wraps a stream of decoded frames (usually a `Framed`) limiting how fast they are yielded.
+    ///
+    /// Errors from the inner stream are passed through without using tokens. `Sink` is forwarded
+    /// untouched, so the wrapper can be used anywhere the inner `Framed` was.
+    #[derive(Debug)]
+    pub struct RateLimited<S, T> {
+        #[pin]
+        inner: S,
+        bucket: TokenBucket,
+        policy: RateLimitPolicy,
+        delay: Option<Pin<Box<Sleep>>>,
+        delayed: Option<T>,
+        is_closed: bool,
+        dropped: u64,
+    }
+}
+
+impl<S, T> RateLimited<S, T> {
+    pub fn new(inner: S, limit: RateLimit, policy: RateLimitPolicy) -> Self {
+        Self {
+            inner,
+            bucket: TokenBucket::new(limit),
+            policy,
+            delay: None,
+            delayed: None,
+            is_closed: false,
+            dropped: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Frames dropped so far by [`RateLimitPolicy::Drop`].
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl<S, T, E> Stream for RateLimited<S, T>
+where
+    S: Stream<Item = Result<T, E>>,
+{
+    type Item = Result<T, RateLimitError<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if *this.is_closed {
+                return Poll::Ready(None);
+            }
+
+            if let Some(delay) = this.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+                *this.delay = None;
+            }
+
+            let item = match this.delayed.take() {
+                Some(item) => item,
+                None => match ready!(this.inner.as_mut().poll_next(cx)) {
+                    Some(Ok(item)) => item,
+                    Some(Err(err)) => return Poll::Ready(Some(Err(RateLimitError::Inner(err)))),
+                    None => return Poll::Ready(None),
+                },
+            };
+
+            let Err(wait) = this.bucket.try_acquire() else {
+                return Poll::Ready(Some(Ok(item)));
+            };
+
+            match this.policy {
+                RateLimitPolicy::Delay => {
+                    *this.delayed = Some(item);
+                    *this.delay = Some(Box::pin(tokio::time::sleep(wait)));
+                }
+                RateLimitPolicy::Drop => *this.dropped += 1,
+                RateLimitPolicy::Close => {
+                    *this.is_closed = true;
+                    return Poll::Ready(Some(Err(RateLimitError::Exceeded)));
+                }
+            }
+        }
+    }
+}
+
+impl<S, T, Item> Sink<Item> for RateLimited<S, T>
+where
+    S: Sink<Item>,
+{
+    type Error = S::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
+        self.project().inner.start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+/// Token buckets per peer, for connectionless protocols.
+#[derive(Debug)]
+pub struct PeerRateLimiter<K = SocketAddr> {
+    limit: RateLimit,
+    buckets: HashMap<K, TokenBucket>,
+    prune_threshold: usize,
+}
+
+impl<K> PeerRateLimiter<K>
+where
+    K: Eq + Hash,
+{
+    const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::new(),
+            prune_threshold: Self::MIN_PRUNE_THRESHOLD,
+        }
+    }
+
+    /// Takes a token from `peer`'s bucket, or returns how long it should wait.
+    pub fn try_acquire(&mut self, peer: K) -> Result<(), Duration> {
+        if self.buckets.len() >= self.prune_threshold {
+            self.prune();
+        }
+
+        let limit = self.limit;
+
+        self.buckets
+            .entry(peer)
+            .or_insert_with(|| TokenBucket::new(limit))
+            .try_acquire()
+    }
+
+    /// Forgets about the peers which have been idle long enough for their bucket to be full.
+    pub fn prune(&mut self) {
+        self.buckets.retain(|_, bucket| !bucket.is_full());
+        self.prune_threshold = (self.buckets.len() * 2).max(Self::MIN_PRUNE_THRESHOLD);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, StreamExt};
+    use std::convert::Infallible;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(RateLimit::new(2, Duration::from_secs(1)));
+
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert_eq!(bucket.try_acquire(), Err(Duration::from_secs(1)));
+
+        tokio::time::advance(Duration::from_millis(1500)).await;
+
+        assert!(bucket.try_acquire().is_ok());
+        assert_eq!(bucket.try_acquire(), Err(Duration::from_millis(500)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_policies() {
+        let limit = RateLimit::new(1, Duration::from_secs(1));
+        let frames = || stream::iter((0..3).map(Ok::<_, Infallible>));
+
+        let start = Instant::now();
+        let delayed = RateLimited::new(frames(), limit, RateLimitPolicy::Delay)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(delayed, [0, 1, 2]);
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        let dropped = RateLimited::new(frames(), limit, RateLimitPolicy::Drop)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(dropped, [0]);
+
+        let mut closed = RateLimited::new(frames(), limit, RateLimitPolicy::Close);
+        assert_eq!(closed.next().await.unwrap().unwrap(), 0);
+        assert!(matches!(
+            closed.next().await,
+            Some(Err(RateLimitError::Exceeded))
+        ));
+        assert!(closed.next().await.is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use futures::{stream::FuturesOrdered, Sink, SinkExt, Stream, StreamExt};
+use thiserror::Error;
+use tower::{Service, ServiceExt};
+
+#[derive(Debug, Error)]
+pub enum ServeError<D, E, S> {
+    #[error("decode error: {0}")]
+    Decode(D),
+    #[error("encode error: {0}")]
+    Encode(E),
+    #[error("service error: {0}")]
+    Service(S),
+}
+
+/// Drives a request/response `service` over a framed connection until the peer closes it.
+///
+/// Requests are handled one at a time, and a response is sent back for every request the service
+/// answers with `Some`. The first decoding, encoding or service error stops the loop.
+///
+/// `transport` is usually a [`Framed`](tokio_util::codec::Framed). Pass it by `&mut` to keep
+/// using it afterwards, e.g. to answer a decoding error.
+pub async fn serve_framed<T, S, Req, Res, DecodeError>(
+    mut transport: T,
+    mut service: S,
+) -> Result<(), ServeError<DecodeError, <T as Sink<Res>>::Error, S::Error>>
+where
+    T: Stream<Item = Result<Req, DecodeError>> + Sink<Res> + Unpin,
+    S: Service<Req, Response = Option<Res>>,
+{
+    while let Some(request) = transport.next().await {
+        let request = request.map_err(ServeError::Decode)?;
+
+        let response = service
+            .ready()
+            .await
+            .map_err(ServeError::Service)?
+            .call(request)
+            .await
+            .map_err(ServeError::Service)?;
+
+        if let Some(response) = response {
+            transport.send(response).await.map_err(ServeError::Encode)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Back-pressure limits of [`serve_pipelined`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    max_in_flight: usize,
+}
+
+impl PipelineConfig {
+    const DEFAULT_MAX_IN_FLIGHT: usize = 32;
+
+    /// Returns a `PipelineConfig` allowing up to `max_in_flight` requests to be handled
+    /// concurrently. Once reached, no more requests are decoded until the oldest one is answered.
+    ///
+    /// # Panics
+    ///
+    /// If `max_in_flight` is zero.
+    pub fn new(max_in_flight: usize) -> Self {
+        assert!(max_in_flight > 0, "max_in_flight must be at least 1");
+
+        Self { max_in_flight }
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_IN_FLIGHT)
+    }
+}
+
+/// Like [`serve_framed`], but keeps decoding requests while previous ones are being handled.
+///
+/// Up to [`PipelineConfig::max_in_flight`] service calls run concurrently, and responses are
+/// written strictly in request order. CPU-heavy services should move their work to
+/// [`spawn_blocking`](tokio::task::spawn_blocking) so they don't stall the connection.
+///
+/// A decoding error stops reading, but the requests received before it are still answered before
+/// the error is returned.
+pub async fn serve_pipelined<T, S, Req, Res, DecodeError>(
+    mut transport: T,
+    mut service: S,
+    config: PipelineConfig,
+) -> Result<(), ServeError<DecodeError, <T as Sink<Res>>::Error, S::Error>>
+where
+    T: Stream<Item = Result<Req, DecodeError>> + Sink<Res> + Unpin,
+    S: Service<Req, Response = Option<Res>>,
+{
+    let mut in_flight = FuturesOrdered::<S::Future>::new();
+    let mut decode_error = None;
+    let mut reading = true;
+
+    loop {
+        tokio::select! {
+            // Answer what we can before reading more
+            biased;
+
+            Some(response) = in_flight.next(), if !in_flight.is_empty() => {
+                if let Some(response) = response.map_err(ServeError::Service)? {
+                    transport.send(response).await.map_err(ServeError::Encode)?;
+                }
+            }
+            request = transport.next(), if reading && in_flight.len() < config.max_in_flight => {
+                match request {
+                    Some(Ok(request)) => {
+                        let call = service
+                            .ready()
+                            .await
+                            .map_err(ServeError::Service)?
+                            .call(request);
+
+                        in_flight.push_back(call);
+                    }
+                    Some(Err(err)) => {
+                        decode_error = Some(err);
+                        reading = false;
+                    }
+                    None => reading = false,
+                }
+            }
+            else => break,
+        }
+    }
+
+    match decode_error {
+        Some(err) => Err(ServeError::Decode(err)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{convert::Infallible, time::Duration};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::codec::{Framed, LinesCodec};
+
+    #[tokio::test]
+    async fn test_serve_framed() {
+        let (mut client, server) = tokio::io::duplex(64);
+
+        let service = tower::service_fn(|line: String| async move {
+            // Only answer non-empty lines
+            Ok::<_, Infallible>((!line.is_empty()).then(|| line.to_uppercase()))
+        });
+
+        let server = tokio::spawn(serve_framed(
+            Framed::new(server, LinesCodec::new()),
+            service,
+        ));
+
+        client.write_all(b"hello\n\nworld\n").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut received = String::new();
+        client.read_to_string(&mut received).await.unwrap();
+
+        assert_eq!(received, "HELLO\nWORLD\n");
+        assert!(server.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_serve_pipelined_keeps_order() {
+        let (mut client, server) = tokio::io::duplex(64);
+
+        // Earlier requests take longer, so they complete in reverse order
+        let service = tower::service_fn(|delay: String| async move {
+            let millis = delay.parse::<u64>().unwrap();
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+
+            Ok::<_, Infallible>(Some(delay))
+        });
+
+        let server = tokio::spawn(serve_pipelined(
+            Framed::new(server, LinesCodec::new()),
+            service,
+            PipelineConfig::new(2),
+        ));
+
+        client.write_all(b"30\n20\n10\n0\n").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut received = String::new();
+        client.read_to_string(&mut received).await.unwrap();
+
+        assert_eq!(received, "30\n20\n10\n0\n");
+        assert!(server.await.unwrap().is_ok());
+    }
+}
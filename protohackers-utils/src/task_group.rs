@@ -0,0 +1,220 @@
+use futures::{Future, FutureExt};
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+enum Failure<E> {
+    Error(E),
+    Panic(Box<dyn Any + Send>),
+}
+
+struct Shared<E> {
+    cancellation_token: CancellationToken,
+    failure: Mutex<Option<Failure<E>>>,
+}
+
+impl<E> Shared<E> {
+    fn fail(&self, failure: Failure<E>) {
+        let mut first_failure = self.failure.lock().unwrap();
+
+        // Siblings failing because of the cancellation are not interesting
+        if first_failure.is_none() {
+            *first_failure = Some(failure);
+        }
+
+        self.cancellation_token.cancel();
+    }
+}
+
+/// A set of tasks that live and die together.
+///
+/// The first task to fail cancels all the others, and its error (or panic) is what
+/// [`TaskGroup::join`] returns. Tasks still running when the group is cancelled are stopped at
+/// their next `.await`.
+pub struct TaskGroup<E> {
+    spawner: TaskSpawner<E>,
+    done_recv: mpsc::Receiver<()>,
+}
+
+/// A cloneable handle spawning tasks into a [`TaskGroup`].
+///
+/// The group can't be joined while handles exist outside its tasks, so don't keep them around
+/// longer than needed.
+pub struct TaskSpawner<E> {
+    shared: Arc<Shared<E>>,
+    // Never sent to, the group knows all its tasks are done once every sender is dropped
+    done_send: mpsc::Sender<()>,
+}
+
+impl<E> TaskGroup<E>
+where
+    E: Send + 'static,
+{
+    pub fn new() -> Self {
+        Self::with_cancellation_token(CancellationToken::new())
+    }
+
+    /// Returns a `TaskGroup` cancelled along with `cancellation_token`.
+    pub fn with_cancellation_token(cancellation_token: CancellationToken) -> Self {
+        let (done_send, done_recv) = mpsc::channel(1);
+
+        Self {
+            spawner: TaskSpawner {
+                shared: Arc::new(Shared {
+                    cancellation_token,
+                    failure: Mutex::new(None),
+                }),
+                done_send,
+            },
+            done_recv,
+        }
+    }
+
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = Result<(), E>> + Send + 'static,
+    {
+        self.spawner.spawn(future);
+    }
+
+    pub fn spawner(&self) -> TaskSpawner<E> {
+        self.spawner.clone()
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        self.spawner.cancellation_token()
+    }
+
+    pub fn cancel(&self) {
+        self.spawner.cancel();
+    }
+
+    /// Waits until the group is cancelled, either explicitly or by a failing task.
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.spawner.cancellation_token().cancelled()
+    }
+
+    /// Waits for all the tasks to finish, returning the first error.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the first task that panicked.
+    pub async fn join(mut self) -> Result<(), E> {
+        let shared = Arc::clone(&self.spawner.shared);
+        drop(self.spawner);
+
+        // Returns `None` once all the tasks (and spawners) are gone
+        let _ = self.done_recv.recv().await;
+
+        let failure = shared.failure.lock().unwrap().take();
+
+        match failure {
+            None => Ok(()),
+            Some(Failure::Error(err)) => Err(err),
+            Some(Failure::Panic(payload)) => panic::resume_unwind(payload),
+        }
+    }
+}
+
+impl<E> Default for TaskGroup<E>
+where
+    E: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> TaskSpawner<E>
+where
+    E: Send + 'static,
+{
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = Result<(), E>> + Send + 'static,
+    {
+        let shared = Arc::clone(&self.shared);
+        let done_send = self.done_send.clone();
+
+        tokio::spawn(async move {
+            let _done_send = done_send;
+
+            let result = tokio::select! {
+                _ = shared.cancellation_token.cancelled() => return,
+                result = AssertUnwindSafe(future).catch_unwind() => result,
+            };
+
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => shared.fail(Failure::Error(err)),
+                Err(payload) => shared.fail(Failure::Panic(payload)),
+            }
+        });
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.shared.cancellation_token
+    }
+
+    pub fn cancel(&self) {
+        self.shared.cancellation_token.cancel();
+    }
+}
+
+impl<E> Clone for TaskSpawner<E> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            done_send: self.done_send.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_first_error_cancels_siblings() {
+        let group = TaskGroup::new();
+
+        group.spawn(async {
+            futures::future::pending::<()>().await;
+            Ok(())
+        });
+        group.spawn(async { Err("failed") });
+
+        assert_eq!(group.join().await, Err("failed"));
+    }
+
+    #[tokio::test]
+    async fn test_join_waits_for_all() {
+        let group = TaskGroup::<()>::new();
+        let (send, mut recv) = mpsc::unbounded_channel();
+
+        for i in 0..3 {
+            let send = send.clone();
+
+            group.spawn(async move {
+                tokio::task::yield_now().await;
+                send.send(i).unwrap();
+                Ok(())
+            });
+        }
+
+        assert_eq!(group.join().await, Ok(()));
+
+        drop(send);
+        let mut received = Vec::new();
+        while let Some(i) = recv.recv().await {
+            received.push(i);
+        }
+        received.sort();
+
+        assert_eq!(received, [0, 1, 2]);
+    }
+}
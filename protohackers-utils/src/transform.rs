@@ -0,0 +1,224 @@
+use pin_project_lite::pin_project;
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// A stateful byte transform applied to everything flowing through a [`TransformStream`].
+///
+/// Implementors only need the per-byte methods. The per-slice ones can be overridden when a
+/// transform can work faster on whole chunks.
+pub trait Transform {
+    /// Transforms a byte read from the underlying stream.
+    fn transform_read_byte(&mut self, byte: u8) -> u8;
+
+    /// Transforms a byte about to be written to the underlying stream.
+    fn transform_write_byte(&mut self, byte: u8) -> u8;
+
+    fn transform_read(&mut self, buf: &mut [u8]) {
+        for byte in buf.iter_mut() {
+            *byte = self.transform_read_byte(*byte);
+        }
+    }
+
+    fn transform_write(&mut self, buf: &mut [u8]) {
+        for byte in buf.iter_mut() {
+            *byte = self.transform_write_byte(*byte);
+        }
+    }
+}
+
+const DEFAULT_WRITE_CAPACITY: usize = 8 * 1024;
+
+pin_project! {
+    /// Wraps a stream applying a [`Transform`] to all bytes read from and written to it.
+    ///
+    /// Reads are transformed in place. Writes are transformed into an internal buffer that is
+    /// reused between calls, so a transform's state is only advanced once per byte even if the
+    /// underlying stream accepts partial writes.
+    #[derive(Debug)]
+    pub struct TransformStream<S, T> {
+        #[pin]
+        inner: S,
+        transform: T,
+        write_buf: Vec<u8>,
+        write_pos: usize,
+        write_capacity: usize,
+    }
+}
+
+impl<S, T> TransformStream<S, T> {
+    pub fn new(inner: S, transform: T) -> Self {
+        Self::with_capacity(DEFAULT_WRITE_CAPACITY, inner, transform)
+    }
+
+    /// Creates a `TransformStream` whose write buffer holds at most `write_capacity` bytes.
+    pub fn with_capacity(write_capacity: usize, inner: S, transform: T) -> Self {
+        Self {
+            inner,
+            transform,
+            write_buf: Vec::with_capacity(write_capacity),
+            write_pos: 0,
+            write_capacity,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn transform(&self) -> &T {
+        &self.transform
+    }
+
+    /// Returns the inner stream and transform. Any buffered but unflushed writes are lost.
+    pub fn into_inner(self) -> (S, T) {
+        (self.inner, self.transform)
+    }
+}
+
+impl<S, T> TransformStream<S, T>
+where
+    S: AsyncWrite,
+{
+    fn poll_flush_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+
+        while *this.write_pos < this.write_buf.len() {
+            let written = ready!(this
+                .inner
+                .as_mut()
+                .poll_write(cx, &this.write_buf[*this.write_pos..]))?;
+
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write transformed bytes",
+                )));
+            }
+
+            *this.write_pos += written;
+        }
+
+        this.write_buf.clear();
+        *this.write_pos = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S, T> AsyncRead for TransformStream<S, T>
+where
+    S: AsyncRead,
+    T: Transform,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let filled_before = buf.filled().len();
+
+        ready!(this.inner.poll_read(cx, buf))?;
+
+        this.transform
+            .transform_read(&mut buf.filled_mut()[filled_before..]);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S, T> AsyncWrite for TransformStream<S, T>
+where
+    S: AsyncWrite,
+    T: Transform,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+
+        let this = self.as_mut().project();
+        let accepted = buf.len().min(*this.write_capacity);
+
+        this.write_buf.extend_from_slice(&buf[..accepted]);
+        this.transform.transform_write(this.write_buf);
+
+        // Eagerly try to push the bytes, they will be retried on the next write or flush anyway
+        if let Poll::Ready(Err(err)) = self.poll_flush_buf(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(accepted))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush_buf(cx))?;
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[derive(Default)]
+    struct XorPos {
+        read_pos: u8,
+        write_pos: u8,
+    }
+
+    impl Transform for XorPos {
+        fn transform_read_byte(&mut self, byte: u8) -> u8 {
+            let byte = byte ^ self.read_pos;
+            self.read_pos = self.read_pos.wrapping_add(1);
+            byte
+        }
+
+        fn transform_write_byte(&mut self, byte: u8) -> u8 {
+            let byte = byte ^ self.write_pos;
+            self.write_pos = self.write_pos.wrapping_add(1);
+            byte
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_with_partial_writes() {
+        // A tiny duplex forces partial writes and reads
+        let (client, server) = tokio::io::duplex(3);
+        let mut client = TransformStream::new(client, XorPos::default());
+        let mut server = TransformStream::with_capacity(2, server, XorPos::default());
+
+        let message = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+
+        let writer = tokio::spawn({
+            let message = message.clone();
+
+            async move {
+                client.write_all(&message).await.unwrap();
+                client.shutdown().await.unwrap();
+            }
+        });
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        writer.await.unwrap();
+
+        assert_eq!(received, message);
+    }
+}